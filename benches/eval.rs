@@ -0,0 +1,100 @@
+// Compares the tree-walking evaluator with the bytecode VM.
+//
+// Run with `cargo bench`.
+use std::time::{Duration, Instant};
+
+use dolang::ast::AST;
+use dolang::eval::env::Env;
+use dolang::eval::eval::eval;
+use dolang::lexer::Lexer;
+use dolang::parser::Parser;
+use dolang::resolver::Resolver;
+use dolang::token::TokenType;
+use dolang::vm::vm::Vm;
+
+const ITERATIONS: u32 = 10;
+
+const PROGRAMS: &[(&str, &str)] = &[
+    (
+        "arithmetic",
+        r#"
+let square = fn x -> x * x
+let total = 1..2000 |> map(square) |> filter(fn x -> x % 3 is 0) |> sum
+"#,
+    ),
+    (
+        "closures",
+        r#"
+let add = fn x, y -> x + y
+let adder = fn n -> fn x -> add(n, x)
+let add2 = adder(2)
+let total = 1..2000 |> map(add2) |> map(add(1)) |> sum
+"#,
+    ),
+    (
+        "records",
+        r#"
+let users = 1..1000 |> map(fn id -> { id: id, name: "user", scores: [id, id, id] })
+let ids = users |> map(fn user -> user.id) |> sum
+let scores = users |> map(fn user -> user.scores) |> map(sum) |> sum
+"#,
+    ),
+    (
+        "append",
+        r#"
+let xs = 1..5000
+let total = xs |> map(fn x -> append(xs, x)) |> map(len) |> sum
+"#,
+    ),
+];
+
+fn parse(source: &str) -> AST {
+    let mut lexer = Lexer::new(source);
+    let mut tokens = Vec::new();
+    loop {
+        let token = lexer.next_token();
+        if token.token_type == TokenType::EOF {
+            break;
+        }
+        tokens.push(token);
+    }
+    let mut ast = Parser::new(tokens)
+        .parse()
+        .expect("failed to parse benchmark");
+    Resolver::new(Env::new(None).names())
+        .resolve(&mut ast)
+        .expect("failed to resolve benchmark");
+    ast
+}
+
+fn measure(mut run: impl FnMut()) -> Duration {
+    run(); // warm up
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        run();
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn main() {
+    println!(
+        "{:<12} {:>14} {:>14} {:>8}",
+        "program", "tree walker", "vm", "speedup"
+    );
+    for (name, source) in PROGRAMS {
+        let ast = parse(source);
+        let tree = measure(|| {
+            eval(ast.clone(), &mut Env::new(None)).expect("tree walker failed");
+        });
+        let vm = measure(|| {
+            Vm::new().run(&ast).expect("vm failed");
+        });
+        println!(
+            "{:<12} {:>14?} {:>14?} {:>7.1}x",
+            name,
+            tree,
+            vm,
+            tree.as_secs_f64() / vm.as_secs_f64()
+        );
+    }
+}
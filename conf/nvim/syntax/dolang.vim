@@ -7,9 +7,9 @@ if exists("b:current_syntax")
 endif
 
 " Keywords
-syntax keyword dolangKeyword let fn if else for in match return and or not is
-syntax keyword dolangBuiltIn print println read_file map filter append first second third tail last int split str len empty not_empty
-syntax keyword dolangBoolean true false
+syntax keyword dolangKeyword let type fn if else for in match try return and or not is with
+syntax keyword dolangBuiltIn print println env_var read_file write_file map filter append first second third tail last int split str len empty not_empty graphemes trim lower upper replace starts_with ends_with join pad_left pad_right lines chars repeat find format regex matches find_all captures replace_all reduce fold sort sort_by reverse unique group_by zip flat_map take drop any all count enumerate partition chunk min max get_or keys values entries from_entries has set remove merge map_values
+syntax keyword dolangBoolean true false none
 
 " Operators
 syntax match dolangOperator /->/
@@ -25,8 +25,12 @@ syntax match dolangIdentifier /\<[a-zA-Z_][a-zA-Z0-9_]*\>/
 syntax match dolangNumber /\v-?\d+(\.\d+)?/
 
 " Strings
-syntax region dolangString start=/"/ end=/"/ contains=dolangEscape
-syntax match dolangEscape /\\./
+syntax region dolangString start=/"""/ skip=/\\./ end=/"""/ contains=dolangEscape,dolangPlaceholder
+syntax region dolangString start=/"\("""\)\@!/ skip=/\\./ end=/"/ contains=dolangEscape,dolangPlaceholder
+syntax region dolangRawString start=/\<r"""/ end=/"""/
+syntax region dolangRawString start=/\<r"\(""\)\@!/ end=/"/
+syntax match dolangEscape /\\\(u{\x\+}\|.\)/
+syntax region dolangPlaceholder matchgroup=dolangEscape start=/{/ end=/}/ contained contains=TOP
 
 " Comments (if supported)
 " syntax match dolangComment /#.*$/
@@ -48,6 +52,7 @@ highlight link dolangPipeOperator Special
 highlight link dolangIdentifier Identifier
 highlight link dolangNumber Number
 highlight link dolangString String
+highlight link dolangRawString String
 highlight link dolangEscape SpecialChar
 highlight link dolangBracket Delimiter
 highlight link dolangComma Delimiter
@@ -1,3 +1,7 @@
+use rust_decimal::Decimal;
+
+use crate::token::Range;
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct AST {
     pub stmts: Vec<Stmt>,
@@ -6,18 +10,74 @@ pub struct AST {
 #[derive(Debug, Clone, PartialEq)]
 pub enum Stmt {
     Expr(Expr),
-    Let { name: String, val: Expr },
+    Let {
+        name: String,
+        val: Expr,
+    },
+    Type {
+        name: String,
+        variants: Vec<Variant>,
+    },
     Print(Expr),
 }
 
+// One alternative of a `type` declaration, e.g. `Rect(w, h)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variant {
+    pub name: String,
+    pub fields: Vec<String>,
+}
+
+impl Variant {
+    // Body of the constructor function, which takes the fields as parameters.
+    pub fn constructor(&self) -> Expr {
+        Expr::Construct {
+            name: self.name.clone(),
+            args: self
+                .fields
+                .iter()
+                .enumerate()
+                .map(|(slot, field)| Expr::Local {
+                    name: field.clone(),
+                    depth: 0,
+                    slot,
+                })
+                .collect(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Pattern {
+    Int(i64),
     Number(f64),
+    Decimal(Decimal),
     String(String),
     Boolean(bool),
+    Unit,
+    Some(Box<Pattern>),
+    None,
+    Ok(Box<Pattern>),
+    Err(Box<Pattern>),
+    Constructor { name: String, args: Vec<Pattern> },
+    // Matches anything, the value is bound to the name in the case body.
+    Binding(String),
     Wildcard,
 }
 
+impl Pattern {
+    // Names bound by the pattern, in the order their values are produced
+    // by `ops::destructure`.
+    pub fn bindings(&self) -> Vec<String> {
+        match self {
+            Pattern::Binding(name) => vec![name.clone()],
+            Pattern::Some(inner) | Pattern::Ok(inner) | Pattern::Err(inner) => inner.bindings(),
+            Pattern::Constructor { args, .. } => args.iter().flat_map(Pattern::bindings).collect(),
+            _ => vec![],
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Case {
     pub pattern: Pattern,
@@ -39,11 +99,24 @@ pub enum Expr {
         cond: Box<Expr>,
         cases: Vec<Case>,
     },
+    // `try body` gives `ok(value)` or `err({ kind, message })`, with a
+    // fallback the value of the body or of the fallback.
+    Try {
+        body: Box<Expr>,
+        fallback: Option<Box<Expr>>,
+    },
     List(Vec<Expr>),
     Record(Vec<(String, Expr)>),
+    // `{ record with field: value }`, a copy of the record with the given
+    // fields replaced.
+    Update {
+        record: Box<Expr>,
+        fields: Vec<(String, Expr)>,
+    },
     Pipe {
         left: Box<Expr>,
         right: Box<Expr>,
+        span: Range, // the stage after `|>`
     },
     Logic {
         left: Box<Expr>,
@@ -85,15 +158,43 @@ pub enum Expr {
     Call {
         name: Box<Expr>,
         args: Vec<Expr>,
+        span: Range, // from the function name to the closing parenthesis
     },
     Access {
         record: Box<Expr>,
         field: String,
     },
+    // Builds a value of a user-defined type. Not written by users, it is the
+    // body of the constructor functions a `type` declaration defines.
+    Construct {
+        name: String,
+        args: Vec<Expr>,
+    },
     Identifier(String),
+    // An identifier the resolver bound to a function parameter: the value
+    // lives in `slot` of the frame `depth` levels up from the current one.
+    Local {
+        name: String,
+        depth: usize,
+        slot: usize,
+    },
+    Int(i64),
     Number(f64),
+    Decimal(Decimal),
     String(String),
+    // A string literal with placeholders, e.g. `"id: {id}"`.
+    Interpolated(Vec<Segment>),
     Boolean(bool),
+    Unit,
+    None,
+}
+
+// A piece of an interpolated string: literal text or the expression of a
+// placeholder, whose value is formatted like `print` does.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment {
+    Text(String),
+    Expr(Expr),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -123,6 +224,7 @@ pub enum TermOp {
 pub enum FactorOp {
     Multiply,
     Divide,
+    IntDivide,
     Modulus,
 }
 #[derive(Debug, Clone, PartialEq)]
@@ -1,4 +1,4 @@
-use crate::ast::{Expr, Stmt, AST};
+use crate::ast::{Expr, Segment, Stmt, AST};
 
 pub fn print_ast(ast: &AST) {
     for stmt in &ast.stmts {
@@ -21,6 +21,16 @@ fn print_stmt(stmt: &Stmt, level: usize) {
             println!("{pad}LetStmt: {name}");
             print_expr(val, level + 1);
         }
+        Stmt::Type { name, variants } => {
+            println!("{pad}TypeStmt: {name}");
+            for variant in variants {
+                println!(
+                    "{pad}  Variant: {}({})",
+                    variant.name,
+                    variant.fields.join(", ")
+                );
+            }
+        }
         Stmt::Print(expr) => {
             println!("{pad}PrintStmt:");
             print_expr(expr, level + 1);
@@ -52,6 +62,14 @@ fn print_expr(expr: &Expr, level: usize) {
                 print_expr(&case.body, level + 2);
             }
         }
+        Expr::Try { body, fallback } => {
+            println!("{pad}Try:");
+            print_expr(body, level + 1);
+            if let Some(fallback) = fallback {
+                println!("{pad}  Else:");
+                print_expr(fallback, level + 2);
+            }
+        }
         Expr::List(items) => {
             println!("{pad}List:");
             for item in items {
@@ -65,7 +83,15 @@ fn print_expr(expr: &Expr, level: usize) {
                 print_expr(value, level + 1);
             }
         }
-        Expr::Pipe { left, right } => {
+        Expr::Update { record, fields } => {
+            println!("{pad}Update:");
+            print_expr(record, level + 1);
+            for (key, value) in fields {
+                println!("{pad}  {key}:");
+                print_expr(value, level + 1);
+            }
+        }
+        Expr::Pipe { left, right, .. } => {
             println!("{pad}Pipe:");
             print_expr(left, level + 1);
             print_expr(right, level + 1);
@@ -121,8 +147,14 @@ fn print_expr(expr: &Expr, level: usize) {
             print_expr(record, level + 1);
             println!("{pad}  Field: {field}");
         }
-        Expr::Call { name, args } => {
-            println!("{pad}Call:");
+        Expr::Construct { name, args } => {
+            println!("{pad}Construct: {name}");
+            for arg in args {
+                print_expr(arg, level + 1);
+            }
+        }
+        Expr::Call { name, args, span } => {
+            println!("{pad}Call: {}:{}", span.start.line, span.start.column);
             print_expr(name, level + 1);
             for arg in args {
                 print_expr(arg, level + 2);
@@ -131,14 +163,38 @@ fn print_expr(expr: &Expr, level: usize) {
         Expr::Identifier(name) => {
             println!("{pad}Identifier: {name}");
         }
+        Expr::Local { name, depth, slot } => {
+            println!("{pad}Local: {name} (depth = {depth}, slot = {slot})");
+        }
+        Expr::Int(n) => {
+            println!("{pad}Int: {n}");
+        }
         Expr::Number(n) => {
             println!("{pad}Number: {n}");
         }
+        Expr::Decimal(d) => {
+            println!("{pad}Decimal: {d}");
+        }
         Expr::String(s) => {
             println!("{pad}String: \"{s}\"");
         }
+        Expr::Interpolated(segments) => {
+            println!("{pad}Interpolated");
+            for segment in segments {
+                match segment {
+                    Segment::Text(text) => println!("{pad}  Text: \"{text}\""),
+                    Segment::Expr(expr) => print_expr(expr, level + 1),
+                }
+            }
+        }
         Expr::Boolean(b) => {
             println!("{pad}Boolean: {b}");
         }
+        Expr::Unit => {
+            println!("{pad}Unit");
+        }
+        Expr::None => {
+            println!("{pad}None");
+        }
     }
 }
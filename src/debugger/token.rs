@@ -10,6 +10,7 @@ fn format_token(token: &Token) -> String {
     let token_type = token.token_type.clone();
     match token_type {
         TokenType::Let => "Let (let)".to_string(),
+        TokenType::Type => "Type (type)".to_string(),
         TokenType::If => "If (if)".to_string(),
         TokenType::Then => "Then (then)".to_string(),
         TokenType::Else => "Else (else)".to_string(),
@@ -18,6 +19,8 @@ fn format_token(token: &Token) -> String {
         TokenType::In => "In (in)".to_string(),
         TokenType::Is => "Is (is)".to_string(),
         TokenType::Match => "Match (match)".to_string(),
+        TokenType::Try => "Try (try)".to_string(),
+        TokenType::With => "With (with)".to_string(),
         TokenType::Pipe => "Pipe (|)".to_string(),
         TokenType::Underscore => "Underscore (_)".to_string(),
         TokenType::ForwardPipe => "ForwardPipe (|>)".to_string(),
@@ -33,6 +36,7 @@ fn format_token(token: &Token) -> String {
         TokenType::Not => "Not (not)".to_string(),
         TokenType::True => "True (true)".to_string(),
         TokenType::False => "False (false)".to_string(),
+        TokenType::None => "None (none)".to_string(),
         TokenType::Comma => "Comma (,)".to_string(),
         TokenType::Dot => "Dot (.)".to_string(),
         TokenType::DotDot => "DotDot (..)".to_string(),
@@ -44,14 +48,20 @@ fn format_token(token: &Token) -> String {
         TokenType::LeftBrace => "LeftBrace ({)".to_string(),
         TokenType::RightBrace => "RightBrace (})".to_string(),
         TokenType::Identifier(s) => format!("Identifier ({s})"),
+        TokenType::Int(n) => format!("Int ({n})"),
         TokenType::Number(n) => format!("Number ({n})"),
+        TokenType::Decimal(d) => format!("Decimal ({d}d)"),
         TokenType::String(s) => format!("String (\"{s}\")"),
         TokenType::Plus => "Plus (+)".to_string(),
         TokenType::Minus => "Minus (-)".to_string(),
         TokenType::Asterisk => "Asterisk (*)".to_string(),
         TokenType::Slash => "Slash (/)".to_string(),
+        TokenType::SlashSlash => "SlashSlash (//)".to_string(),
         TokenType::Percent => "Percent (%)".to_string(),
         TokenType::Invalid => "Invalid".to_string(),
+        TokenType::UnterminatedString => "UnterminatedString".to_string(),
+        TokenType::IntOutOfRange(text) => format!("IntOutOfRange ({})", text),
+        TokenType::Interpolated(parts) => format!("Interpolated ({} parts)", parts.len()),
         TokenType::EOF => "EOF".to_string(),
     }
 }
@@ -1,18 +1,46 @@
-use crate::eval::builtin::etc::{empty, len, not_empty};
-use crate::eval::builtin::file::read_file;
-use crate::eval::builtin::io::{print, println};
-use crate::eval::builtin::list::{append, filter, first, last, map, second, sum, tail, third};
-use crate::eval::builtin::num::str;
-use crate::eval::builtin::str::{int, split};
-use crate::eval::value::Value;
+use crate::eval::builtin::error::{assert, fail};
+use crate::eval::builtin::etc::{empty, find, len, not_empty};
+use crate::eval::builtin::file::{read_file, write_file};
+use crate::eval::builtin::io::{env_var, print, println, read_line};
+use crate::eval::builtin::list::{
+    all, any, append, chunk, count, drop, enumerate, filter, first, first_opt, flat_map, fold, get,
+    get_or, group_by, last, map, max, min, partition, reduce, reverse, second, sort, sort_by, sum,
+    tail, take, third, unique, zip,
+};
+use crate::eval::builtin::num::{decimal, round, round_with, str};
+use crate::eval::builtin::option::{err, ok, some};
+use crate::eval::builtin::record::{
+    entries, from_entries, has, keys, map_values, merge, remove, set, values,
+};
+use crate::eval::builtin::regex::{captures, find_all, matches, regex, replace_all};
+use crate::eval::builtin::str::{
+    chars, ends_with, float, format, graphemes, int, join, lines, lower, pad_left, pad_right,
+    parse_int, repeat, replace, split, starts_with, trim, upper,
+};
+use crate::eval::context::Context;
+use crate::eval::error::Error;
+use crate::eval::value::{BuiltinFuncArgs, NativeFn, Value};
 
 pub struct BuiltinFunc {
     pub name: &'static str,
-    pub func: fn(Vec<Value>) -> Result<Value, String>,
+    pub func: fn(&Context, Vec<Value>) -> Result<Value, Error>,
     pub args_len: usize,
     pub description: &'static str,
 }
 
+impl BuiltinFunc {
+    pub fn to_value(&self) -> Value {
+        Value::BuiltinFunc {
+            name: self.name.to_string(),
+            func: NativeFn::new(self.func),
+            args: BuiltinFuncArgs {
+                length: self.args_len,
+                curried: vec![],
+            },
+        }
+    }
+}
+
 pub const BUILTIN_FUNCTIONS: &[BuiltinFunc] = &[
     // IO
     BuiltinFunc {
@@ -35,17 +63,49 @@ This function is useful for displaying information in a readable format.
 Example:
         println("Hello, World!")"#,
     },
+    BuiltinFunc {
+        name: "read_line",
+        func: read_line,
+        args_len: 0,
+        description: r#"Reads a line from the standard input, without its line ending.
+Returns none once the input is exhausted.
+
+Example:
+        print("Name? ")
+        match read_line() | some(name) -> println("Hi " + name) | none -> ()"#,
+    },
+    BuiltinFunc {
+        name: "env_var",
+        func: env_var,
+        args_len: 1,
+        description: r#"Returns the value of an environment variable as an option, none when it isn't set.
+In a sandbox, reading the environment gives a permission error unless it is allowed.
+
+Example:
+        env_var("HOME") # some("/home/ada")"#,
+    },
     // File
     BuiltinFunc {
         name: "read_file",
         func: read_file,
         args_len: 1,
         description: r#"Reads the contents of a file and returns it as a string.
-This function is useful for reading data from files.
+This function is useful for reading data from files. In a sandbox, files outside the allowed directories give a permission error.
 
 Example:
         read_file("path/to/file.txt")"#,
     },
+    BuiltinFunc {
+        name: "write_file",
+        func: write_file,
+        args_len: 2,
+        description: r#"Writes a string to a file, replacing its contents or creating it.
+In a sandbox, files outside the allowed directories give a permission error.
+
+Example:
+        write_file("out.txt", "hello")
+        report |> write_file("report.txt")"#,
+    },
     // List
     BuiltinFunc {
         name: "map",
@@ -85,6 +145,39 @@ This function is useful for accessing the first item in a list.
 Example:
         first([1, 2, 3]) # 1 "#,
     },
+    BuiltinFunc {
+        name: "first_opt",
+        func: first_opt,
+        args_len: 1,
+        description: r#"Returns the first element of a list as an option.
+Unlike `first`, an empty list gives `none` instead of an error.
+
+Example:
+        first_opt([1, 2, 3]) # some(1)
+        first_opt([]) # none"#,
+    },
+    BuiltinFunc {
+        name: "get",
+        func: get,
+        args_len: 2,
+        description: r#"Returns the element of a list at an index, or the field of a record, as an option.
+Missing elements and fields give `none` instead of an error.
+
+Example:
+        get(1, [1, 2, 3]) # some(2)
+        {name: "Mike"} |> get("age") # none"#,
+    },
+    BuiltinFunc {
+        name: "get_or",
+        func: get_or,
+        args_len: 3,
+        description: r#"Returns the element of a list at an index, or the field of a record,
+or the default when it is missing.
+
+Example:
+        get_or(5, 0, [1, 2, 3]) # 0
+        config |> get_or("port", 8080)"#,
+    },
     BuiltinFunc {
         name: "second",
         func: second,
@@ -135,16 +228,223 @@ This function is useful for calculating the total of numeric lists.
 Example:
         sum([1, 2, 3]) # 6 "#,
     },
+    BuiltinFunc {
+        name: "reduce",
+        func: reduce,
+        args_len: 2,
+        description: r#"Combines the elements of a list from the left with a function of the
+result so far and the next element, starting from the first element.
+An empty list is an error, use `fold` when it can be empty.
+
+Example:
+        reduce(fn a, b -> if a > b then a else b, [3, 7, 5]) # 7"#,
+    },
+    BuiltinFunc {
+        name: "fold",
+        func: fold,
+        args_len: 3,
+        description: r#"Combines the elements of a list from the left with a function of the
+result so far and the next element, starting from an initial value.
+
+Example:
+        fold(fn acc, x -> acc + x * x, 0, [1, 2, 3]) # 14"#,
+    },
+    BuiltinFunc {
+        name: "sort",
+        func: sort,
+        args_len: 1,
+        description: r#"Sorts a list of numbers or strings in ascending order.
+Values that cannot be compared with each other are an error.
+
+Example:
+        sort([3, 1, 2]) # [1, 2, 3]"#,
+    },
+    BuiltinFunc {
+        name: "sort_by",
+        func: sort_by,
+        args_len: 2,
+        description: r#"Sorts a list by the key a function gives each element.
+Elements with equal keys keep their order.
+
+Example:
+        users |> sort_by(fn u -> u.age)"#,
+    },
+    BuiltinFunc {
+        name: "reverse",
+        func: reverse,
+        args_len: 1,
+        description: r#"Reverses the order of a list.
+
+Example:
+        reverse([1, 2, 3]) # [3, 2, 1]"#,
+    },
+    BuiltinFunc {
+        name: "unique",
+        func: unique,
+        args_len: 1,
+        description: r#"Removes the elements equal to an earlier one, keeping the order.
+
+Example:
+        unique([1, 2, 1, 3, 2]) # [1, 2, 3]"#,
+    },
+    BuiltinFunc {
+        name: "group_by",
+        func: group_by,
+        args_len: 2,
+        description: r#"Groups the elements of a list into a record of lists, by the key a
+function gives each element. Keys that are not strings are written the way
+print writes them.
+
+Example:
+        group_by(fn n -> if n % 2 is 0 then "even" else "odd", [1, 2, 3])
+        # {odd: [1, 3], even: [2]}"#,
+    },
+    BuiltinFunc {
+        name: "zip",
+        func: zip,
+        args_len: 2,
+        description: r#"Pairs up the elements of two lists into a list of two-element lists.
+The result is as long as the shorter list.
+
+Example:
+        zip([1, 2, 3], ["a", "b"]) # [[1, "a"], [2, "b"]]"#,
+    },
+    BuiltinFunc {
+        name: "flat_map",
+        func: flat_map,
+        args_len: 2,
+        description: r#"Applies a function returning a list to each element and concatenates
+the results.
+
+Example:
+        flat_map(fn x -> [x, x * 10], [1, 2]) # [1, 10, 2, 20]"#,
+    },
+    BuiltinFunc {
+        name: "take",
+        func: take,
+        args_len: 2,
+        description: r#"Returns the first n elements of a list, or all of them if it is shorter.
+
+Example:
+        take(2, [1, 2, 3]) # [1, 2]"#,
+    },
+    BuiltinFunc {
+        name: "drop",
+        func: drop,
+        args_len: 2,
+        description: r#"Returns a list without its first n elements.
+
+Example:
+        drop(2, [1, 2, 3]) # [3]"#,
+    },
+    BuiltinFunc {
+        name: "any",
+        func: any,
+        args_len: 2,
+        description: r#"Checks if a function returns true for at least one element of a list.
+
+Example:
+        any(fn x -> x > 2, [1, 2, 3]) # true"#,
+    },
+    BuiltinFunc {
+        name: "all",
+        func: all,
+        args_len: 2,
+        description: r#"Checks if a function returns true for every element of a list.
+
+Example:
+        all(fn x -> x > 2, [1, 2, 3]) # false"#,
+    },
+    BuiltinFunc {
+        name: "count",
+        func: count,
+        args_len: 2,
+        description: r#"Counts the elements of a list a function returns true for.
+
+Example:
+        count(fn x -> x > 1, [1, 2, 3]) # 2"#,
+    },
+    BuiltinFunc {
+        name: "enumerate",
+        func: enumerate,
+        args_len: 1,
+        description: r#"Pairs each element of a list with its index.
+
+Example:
+        enumerate(["a", "b"]) # [[0, "a"], [1, "b"]]"#,
+    },
+    BuiltinFunc {
+        name: "partition",
+        func: partition,
+        args_len: 2,
+        description: r#"Splits a list in two: the elements a function returns true for, and
+the others.
+
+Example:
+        partition(fn x -> x > 1, [1, 2, 3]) # [[2, 3], [1]]"#,
+    },
+    BuiltinFunc {
+        name: "chunk",
+        func: chunk,
+        args_len: 2,
+        description: r#"Cuts a list into lists of a given size, the last one possibly shorter.
+
+Example:
+        chunk(2, [1, 2, 3]) # [[1, 2], [3]]"#,
+    },
+    BuiltinFunc {
+        name: "min",
+        func: min,
+        args_len: 1,
+        description: r#"Returns the smallest element of a list of numbers or strings.
+An empty list is an error.
+
+Example:
+        min([3, 1, 2]) # 1"#,
+    },
+    BuiltinFunc {
+        name: "max",
+        func: max,
+        args_len: 1,
+        description: r#"Returns the largest element of a list of numbers or strings.
+An empty list is an error.
+
+Example:
+        max([3, 1, 2]) # 3"#,
+    },
     // String
     BuiltinFunc {
         name: "int",
         func: int,
         args_len: 1,
-        description: r#"Converts a string to an integer.
-This function is useful for parsing numeric strings.
+        description: r#"Converts a string or a float to an integer.
+Floats are truncated toward zero, invalid strings are an error.
+
+Example:
+        int("123") # 123
+        int(3.7) # 3"#,
+    },
+    BuiltinFunc {
+        name: "parse_int",
+        func: parse_int,
+        args_len: 1,
+        description: r#"Parses a string as an integer and returns a result.
+Unlike `int`, invalid input gives an `err` with a message instead of failing the script.
+
+Example:
+        parse_int("42") # ok(42)
+        parse_int("abc") # err(invalid integer: abc)"#,
+    },
+    BuiltinFunc {
+        name: "float",
+        func: float,
+        args_len: 1,
+        description: r#"Converts a string or an integer to a float.
+This function is useful when integer operands should produce a fractional result.
 
 Example:
-        int("123") # 123 "#,
+        float("1.5") # 1.5
+        float(3) # 3.0"#,
     },
     BuiltinFunc {
         name: "split",
@@ -156,6 +456,287 @@ This function is useful for breaking down strings into manageable parts.
 Example:
         split("a,b,c", ",") # ["a", "b", "c"]"#,
     },
+    BuiltinFunc {
+        name: "graphemes",
+        func: graphemes,
+        args_len: 1,
+        description: r#"Splits a string into its grapheme clusters, the characters as a reader
+sees them. Indexing, slicing and len count Unicode code points instead, so
+an accented letter written with a combining mark counts as two.
+
+Example:
+        graphemes("e\u{301}t\u{e9}") # ["é", "t", "é"]
+        len(graphemes("👍🏽")) # 1"#,
+    },
+    BuiltinFunc {
+        name: "trim",
+        func: trim,
+        args_len: 1,
+        description: r#"Removes the whitespace at both ends of a string.
+This function is useful for cleaning up input and the fields of split lines.
+
+Example:
+        trim("  hello \n") # "hello""#,
+    },
+    BuiltinFunc {
+        name: "lower",
+        func: lower,
+        args_len: 1,
+        description: r#"Converts a string to lowercase.
+
+Example:
+        lower("Hello") # "hello""#,
+    },
+    BuiltinFunc {
+        name: "upper",
+        func: upper,
+        args_len: 1,
+        description: r#"Converts a string to uppercase.
+
+Example:
+        upper("Hello") # "HELLO""#,
+    },
+    BuiltinFunc {
+        name: "replace",
+        func: replace,
+        args_len: 3,
+        description: r#"Replaces every occurrence of a substring with another string.
+
+Example:
+        replace("-", "/", "2024-01-31") # "2024/01/31"
+        dates |> map(replace("-", "/"))"#,
+    },
+    BuiltinFunc {
+        name: "starts_with",
+        func: starts_with,
+        args_len: 2,
+        description: r#"Checks if a string starts with a prefix.
+
+Example:
+        starts_with("https://", "https://example.com") # true
+        urls |> filter(starts_with("https://"))"#,
+    },
+    BuiltinFunc {
+        name: "ends_with",
+        func: ends_with,
+        args_len: 2,
+        description: r#"Checks if a string ends with a suffix.
+
+Example:
+        ends_with(".csv", "data.csv") # true"#,
+    },
+    BuiltinFunc {
+        name: "join",
+        func: join,
+        args_len: 2,
+        description: r#"Joins the items of a list into a string, with a separator between them.
+Items that are not strings are written the way print writes them.
+
+Example:
+        join(", ", ["a", "b", "c"]) # "a, b, c"
+        [1, 2, 3] |> join("-") # "1-2-3""#,
+    },
+    BuiltinFunc {
+        name: "pad_left",
+        func: pad_left,
+        args_len: 3,
+        description: r#"Pads a string on the left with a fill character up to a width.
+Strings that are already as wide are returned unchanged.
+
+Example:
+        pad_left(5, "0", "42") # "00042""#,
+    },
+    BuiltinFunc {
+        name: "pad_right",
+        func: pad_right,
+        args_len: 3,
+        description: r#"Pads a string on the right with a fill character up to a width.
+Strings that are already as wide are returned unchanged.
+
+Example:
+        pad_right(6, ".", "abc") # "abc...""#,
+    },
+    BuiltinFunc {
+        name: "lines",
+        func: lines,
+        args_len: 1,
+        description: r#"Splits a string into its lines, without the line breaks.
+Both "\n" and "\r\n" end a line, and a final line break doesn't add an empty line.
+
+Example:
+        lines("a\nb\n") # ["a", "b"]"#,
+    },
+    BuiltinFunc {
+        name: "chars",
+        func: chars,
+        args_len: 1,
+        description: r#"Splits a string into its characters, as strings of one Unicode code point.
+
+Example:
+        chars("日本") # ["日", "本"]"#,
+    },
+    BuiltinFunc {
+        name: "repeat",
+        func: repeat,
+        args_len: 2,
+        description: r#"Repeats a string a number of times.
+
+Example:
+        repeat(3, "ab") # "ababab""#,
+    },
+    BuiltinFunc {
+        name: "format",
+        func: format,
+        args_len: 2,
+        description: r#"Fills the placeholders of a template with the items of a list or the
+fields of a record. `{}` takes the next item, `{0}` the item at an index and
+`{name}` a field; `{{` and `}}` are literal braces. Write templates as raw
+strings so that their placeholders aren't interpolated.
+
+Example:
+        format(r"{} is {}", ["Ada", 36]) # "Ada is 36"
+        user |> format(r"{name} is {age}")"#,
+    },
+    // Record
+    BuiltinFunc {
+        name: "keys",
+        func: keys,
+        args_len: 1,
+        description: r#"Returns the field names of a record, sorted.
+
+Example:
+        keys({name: "Ada", age: 36}) # ["age", "name"]"#,
+    },
+    BuiltinFunc {
+        name: "values",
+        func: values,
+        args_len: 1,
+        description: r#"Returns the field values of a record, sorted by field name.
+
+Example:
+        values({name: "Ada", age: 36}) # [36, "Ada"]"#,
+    },
+    BuiltinFunc {
+        name: "entries",
+        func: entries,
+        args_len: 1,
+        description: r#"Returns the fields of a record as [key, value] pairs, sorted by key.
+
+Example:
+        entries({name: "Ada", age: 36}) # [["age", 36], ["name", "Ada"]]"#,
+    },
+    BuiltinFunc {
+        name: "from_entries",
+        func: from_entries,
+        args_len: 1,
+        description: r#"Builds a record from [key, value] pairs. Keys must be strings, and
+later pairs override earlier ones with the same key.
+
+Example:
+        from_entries([["name", "Ada"], ["age", 36]]) # {name: "Ada", age: 36}
+        user |> entries |> filter(fn e -> e[0] is not "password") |> from_entries"#,
+    },
+    BuiltinFunc {
+        name: "has",
+        func: has,
+        args_len: 2,
+        description: r#"Checks if a record has a field.
+
+Example:
+        has("name", {name: "Ada"}) # true"#,
+    },
+    BuiltinFunc {
+        name: "set",
+        func: set,
+        args_len: 3,
+        description: r#"Returns a copy of a record with a field set, adding it if it is missing.
+
+Example:
+        set("age", 37, {name: "Ada", age: 36}) # {name: "Ada", age: 37}
+        user |> set("admin", true)"#,
+    },
+    BuiltinFunc {
+        name: "remove",
+        func: remove,
+        args_len: 2,
+        description: r#"Returns a copy of a record without a field. Missing fields are ignored.
+
+Example:
+        remove("password", {name: "Ada", password: "secret"}) # {name: "Ada"}"#,
+    },
+    BuiltinFunc {
+        name: "merge",
+        func: merge,
+        args_len: 2,
+        description: r#"Adds the fields of the first record to the second. Fields of the first
+record win over the fields both have.
+
+Example:
+        merge({port: 80}, {host: "localhost", port: 8080}) # {host: "localhost", port: 80}
+        defaults |> merge(options)"#,
+    },
+    BuiltinFunc {
+        name: "map_values",
+        func: map_values,
+        args_len: 2,
+        description: r#"Applies a function to each field value of a record and returns a new record.
+
+Example:
+        map_values(fn x -> x * 2, {a: 1, b: 2}) # {a: 2, b: 4}"#,
+    },
+    // Regex
+    BuiltinFunc {
+        name: "regex",
+        func: regex,
+        args_len: 1,
+        description: r#"Compiles a regular expression, failing on an invalid pattern.
+The regex functions also take pattern strings, compiling them on each call;
+compile a regex once to use it on many strings. Raw strings keep backslashes
+and braces as they are.
+
+Example:
+        let date = regex(r"(?P<year>\d{4})-(?P<month>\d{2})")"#,
+    },
+    BuiltinFunc {
+        name: "matches",
+        func: matches,
+        args_len: 2,
+        description: r#"Checks if a regex matches anywhere in a string.
+
+Example:
+        matches(r"^\d+$", "123") # true
+        lines |> filter(matches("ERROR"))"#,
+    },
+    BuiltinFunc {
+        name: "find_all",
+        func: find_all,
+        args_len: 2,
+        description: r#"Returns every match of a regex in a string, as a list of strings.
+
+Example:
+        find_all(r"\d+", "a1b22c333") # ["1", "22", "333"]"#,
+    },
+    BuiltinFunc {
+        name: "captures",
+        func: captures,
+        args_len: 2,
+        description: r#"Returns the named groups of the first match of a regex as an option
+of a record, none when the regex doesn't match. Groups that take no part in
+the match are empty strings.
+
+Example:
+        captures(r"(?P<key>\w+)=(?P<value>\w+)", "id=42") # some({key: id, value: 42})"#,
+    },
+    BuiltinFunc {
+        name: "replace_all",
+        func: replace_all,
+        args_len: 3,
+        description: r#"Replaces every match of a regex in a string. The replacement can refer
+to groups as $1 or $name, and $$ stands for a dollar sign.
+
+Example:
+        replace_all(r"(\d+)-(\d+)", "$2-$1", "10-20") # "20-10""#,
+    },
     // Number
     BuiltinFunc {
         name: "str",
@@ -167,17 +748,107 @@ This function is useful for formatting numbers as strings.
 Example:
         str(123) # "123""#,
     },
+    BuiltinFunc {
+        name: "decimal",
+        func: decimal,
+        args_len: 1,
+        description: r#"Converts a string or a number to a decimal.
+Decimals are exact in base 10, which makes them suited for money. `12.30d` is the literal form.
+
+Example:
+        decimal("12.30") # 12.30
+        decimal("0.1") + decimal("0.2") # 0.3"#,
+    },
+    BuiltinFunc {
+        name: "round",
+        func: round,
+        args_len: 2,
+        description: r#"Rounds a number to the given number of decimal places.
+Halves are rounded away from zero, use `round_with` for other modes.
+
+Example:
+        round(2, 2.675d) # 2.68
+        12.345d |> round(1) # 12.3"#,
+    },
+    BuiltinFunc {
+        name: "round_with",
+        func: round_with,
+        args_len: 3,
+        description: r#"Rounds a number to the given number of decimal places using a rounding mode.
+Modes: "half_up", "half_down", "half_even", "up", "down", "ceil" and "floor".
+
+Example:
+        round_with("half_even", 2, 2.665d) # 2.66
+        1.21d |> round_with("ceil", 1) # 1.3"#,
+    },
+    // Option and Result
+    BuiltinFunc {
+        name: "some",
+        func: some,
+        args_len: 1,
+        description: r#"Wraps a value in an option that holds it. `none` is the empty option.
+Options are taken apart with `match`.
+
+Example:
+        some(1) # some(1)
+        match first_opt(xs) | some(x) -> x | none -> 0"#,
+    },
+    BuiltinFunc {
+        name: "ok",
+        func: ok,
+        args_len: 1,
+        description: r#"Wraps a value in a successful result.
+Results are taken apart with `match`.
+
+Example:
+        ok(1) # ok(1)
+        match parse_int(s) | ok(n) -> n | err(e) -> 0"#,
+    },
+    BuiltinFunc {
+        name: "err",
+        func: err,
+        args_len: 1,
+        description: r#"Wraps a value, usually an error message, in a failed result.
+Results are taken apart with `match`.
+
+Example:
+        err("not found") # err(not found)"#,
+    },
+    // Errors
+    BuiltinFunc {
+        name: "fail",
+        func: fail,
+        args_len: 1,
+        description: r#"Stops the script with an error of kind "user" and the given message.
+The error can be caught with `try`.
+
+Example:
+        if n < 0 then fail("n must be positive") else n
+        try fail("boom") # err({ kind: user, message: boom })"#,
+    },
+    BuiltinFunc {
+        name: "assert",
+        func: assert,
+        args_len: 2,
+        description: r#"Stops the script with an error of kind "assertion" and the given message
+unless the condition is true. Returns () otherwise.
+
+Example:
+        assert(len(xs) > 0, "xs must not be empty")"#,
+    },
     // etc
     BuiltinFunc {
         name: "len",
         func: len,
         args_len: 1,
         description: r#"Returns the length of a list or string.
-This function is useful for determining the size of collections.
+This function is useful for determining the size of collections. Strings
+are measured in Unicode code points, not bytes.
 
 Example:
         len([1, 2, 3]) # 3
-        len("hello") # 5"#,
+        len("hello") # 5
+        len("日本") # 2"#,
     },
     BuiltinFunc {
         name: "empty",
@@ -201,4 +872,17 @@ Example:
         not_empty([1, 2, 3]) # true
         not_empty("") # false"#,
     },
+    BuiltinFunc {
+        name: "find",
+        func: find,
+        args_len: 2,
+        description: r#"Returns the first element of a list a function returns true for, or
+the index of the first occurrence of a substring in a string, counted in
+characters like string indexing. Either is an option.
+
+Example:
+        find(fn x -> x > 1, [1, 2, 3]) # some(2)
+        find("lo", "hello") # some(3)
+        find("x", "hello") # none"#,
+    },
 ];
@@ -0,0 +1,20 @@
+use crate::eval::context::Context;
+use crate::eval::error::{Error, ErrorKind};
+use crate::eval::value::Value;
+
+pub fn fail(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [Value::String(message)] => Err(ctx.raise(ErrorKind::User, message.clone())),
+        _ => Err(ctx.error("fail: expected a message")),
+    }
+}
+
+pub fn assert(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [Value::Boolean(true), Value::String(_)] => Ok(Value::Unit),
+        [Value::Boolean(false), Value::String(message)] => {
+            Err(ctx.raise(ErrorKind::Assertion, message.clone()))
+        }
+        _ => Err(ctx.error("assert: expected a boolean and a message")),
+    }
+}
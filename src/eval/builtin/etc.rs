@@ -1,25 +1,50 @@
+use crate::eval::builtin::list::accepts;
+use crate::eval::context::Context;
+use crate::eval::error::Error;
 use crate::eval::value::Value;
 
-pub fn len(args: Vec<Value>) -> Result<Value, String> {
+pub fn len(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
     match args.as_slice() {
-        [Value::List(l)] => Ok(Value::Number(l.len() as f64)),
-        [Value::String(s)] => Ok(Value::Number(s.len() as f64)),
-        _ => Err("len: expected a list or string".to_string()),
+        [Value::List(l)] => Ok(Value::Int(l.len() as i64)),
+        [Value::String(s)] => Ok(Value::Int(s.chars().count() as i64)),
+        _ => Err(ctx.error("len: expected a list or string")),
     }
 }
 
-pub fn empty(args: Vec<Value>) -> Result<Value, String> {
+pub fn empty(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
     match args.as_slice() {
         [Value::List(l)] => Ok(Value::Boolean(l.is_empty())),
         [Value::String(s)] => Ok(Value::Boolean(s.is_empty())),
-        _ => Err("empty: expected a list or string".to_string()),
+        _ => Err(ctx.error("empty: expected a list or string")),
     }
 }
 
-pub fn not_empty(args: Vec<Value>) -> Result<Value, String> {
+pub fn not_empty(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
     match args.as_slice() {
         [Value::List(l)] => Ok(Value::Boolean(!l.is_empty())),
         [Value::String(s)] => Ok(Value::Boolean(!s.is_empty())),
-        _ => Err("notEmpty: expected a list or string".to_string()),
+        _ => Err(ctx.error("notEmpty: expected a list or string")),
+    }
+}
+
+// The first item a function accepts in a list, or the character index of
+// the first occurrence of a substring, consistent with string indexing.
+pub fn find(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [func, Value::List(items)] if func.is_function() => {
+            for item in items {
+                if accepts(ctx, "find", func, item)? {
+                    return Ok(Value::Option(Some(Box::new(item.clone()))));
+                }
+            }
+            Ok(Value::Option(None))
+        }
+        [Value::String(needle), Value::String(s)] => {
+            let index = s
+                .find(needle.as_str())
+                .map(|byte| Box::new(Value::Int(s[..byte].chars().count() as i64)));
+            Ok(Value::Option(index))
+        }
+        _ => Err(ctx.error("find: expected a function and a list, or a substring and a string")),
     }
 }
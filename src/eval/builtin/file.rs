@@ -1,11 +1,25 @@
+use crate::eval::context::Context;
+use crate::eval::error::Error;
 use crate::eval::value::Value;
 
-pub fn read_file(args: Vec<Value>) -> Result<Value, String> {
+pub fn read_file(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
     match args.as_slice() {
         [Value::String(path)] => {
-            let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+            ctx.check_read(path)?;
+            let content = std::fs::read_to_string(path).map_err(|e| ctx.error(e.to_string()))?;
             Ok(Value::String(content))
         }
-        _ => Err("read_file: expected a string".to_string()),
+        _ => Err(ctx.error("read_file: expected a string")),
+    }
+}
+
+pub fn write_file(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [Value::String(path), Value::String(content)] => {
+            ctx.check_write(path)?;
+            std::fs::write(path, content).map_err(|e| ctx.error(e.to_string()))?;
+            Ok(Value::Unit)
+        }
+        _ => Err(ctx.error("write_file: expected a path and a string")),
     }
 }
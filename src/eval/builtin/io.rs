@@ -1,10 +1,28 @@
+use crate::eval::context::Context;
+use crate::eval::error::Error;
 use crate::eval::value::Value;
 
-fn format_value(value: &Value) -> String {
+pub fn format_value(value: &Value) -> String {
     match value {
-        Value::Number(n) => n.to_string(),
+        Value::Int(n) => n.to_string(),
+        // Debug formatting keeps the fraction, so `3.0` doesn't print as `3`.
+        Value::Number(n) => format!("{:?}", n),
+        Value::Decimal(d) => d.to_string(),
         Value::String(s) => s.clone(),
         Value::Boolean(b) => b.to_string(),
+        Value::Unit => "()".to_string(),
+        Value::Option(Some(v)) => format!("some({})", format_value(v)),
+        Value::Option(None) => "none".to_string(),
+        Value::Result(Ok(v)) => format!("ok({})", format_value(v)),
+        Value::Result(Err(e)) => format!("err({})", format_value(e)),
+        Value::Variant { tag, fields } => {
+            if fields.is_empty() {
+                tag.clone()
+            } else {
+                let items: Vec<String> = fields.iter().map(format_value).collect();
+                format!("{}({})", tag, items.join(", "))
+            }
+        }
         Value::List(l) => {
             let items: Vec<String> = l.iter().map(format_value).collect();
             format!("[{}]", items.join(", "))
@@ -16,6 +34,7 @@ fn format_value(value: &Value) -> String {
                 .collect();
             format!("{{{}}}", items.join(", "))
         }
+        Value::Regex(regex) => format!("regex({})", regex.0.as_str()),
         Value::Func {
             params, body: _, ..
         } => {
@@ -23,23 +42,65 @@ fn format_value(value: &Value) -> String {
             format!("Function: {}", params_str)
         }
         Value::BuiltinFunc { name, .. } => format!("Builtin function: {}", name),
+        Value::Closure(closure) => format!("Function: {}", closure.proto.params.join(", ")),
     }
 }
 
-pub fn print(args: Vec<Value>) -> Result<Value, String> {
+pub fn print(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
     if args.len() != 1 {
-        return Err("print: expected one argument".to_string());
+        return Err(ctx.error("print: expected one argument"));
     }
     let arg = &args[0];
-    print!("{}", format_value(arg));
-    Ok(Value::Number(0.0)) // Return a dummy
+    let mut stdout = ctx.stdout();
+    // Flushed so that a prompt shows up before the script reads an answer.
+    write!(stdout, "{}", format_value(arg))
+        .and_then(|_| stdout.flush())
+        .map_err(|e| ctx.error(format!("print: {}", e)))?;
+    Ok(Value::Unit)
 }
 
-pub fn println(args: Vec<Value>) -> Result<Value, String> {
+pub fn println(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
     if args.len() != 1 {
-        return Err("println: expected one argument".to_string());
+        return Err(ctx.error("println: expected one argument"));
     }
     let arg = &args[0];
-    println!("{}", format_value(arg));
-    Ok(Value::Number(0.0)) // Return a dummy
+    writeln!(ctx.stdout(), "{}", format_value(arg))
+        .map_err(|e| ctx.error(format!("println: {}", e)))?;
+    Ok(Value::Unit)
+}
+
+// Reads a line from the standard input, without its line ending.
+pub fn read_line(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    if !args.is_empty() {
+        return Err(ctx.error("read_line: expected no arguments"));
+    }
+    let mut line = String::new();
+    let read = ctx
+        .stdin()
+        .read_line(&mut line)
+        .map_err(|e| ctx.error(format!("read_line: {}", e)))?;
+    if read == 0 {
+        return Ok(Value::Option(None));
+    }
+    let line = line.strip_suffix('\n').unwrap_or(&line);
+    let line = line.strip_suffix('\r').unwrap_or(line);
+    Ok(Value::Option(Some(Box::new(Value::String(
+        line.to_string(),
+    )))))
+}
+
+// The value of an environment variable, none when it isn't set or isn't
+// valid unicode.
+pub fn env_var(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [Value::String(name)] => {
+            ctx.check_env(name)?;
+            Ok(Value::Option(
+                std::env::var(name)
+                    .ok()
+                    .map(|value| Box::new(Value::String(value))),
+            ))
+        }
+        _ => Err(ctx.error("env_var: expected a string")),
+    }
 }
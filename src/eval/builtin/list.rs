@@ -1,142 +1,559 @@
-use crate::eval::eval::eval_expr;
+use std::cmp::Ordering;
+
+use crate::ast::TermOp;
+use crate::eval::builtin::io::format_value;
+use crate::eval::context::Context;
+use crate::eval::error::Error;
+use crate::eval::ops;
 use crate::eval::value::Value;
 
-pub fn map(args: Vec<Value>) -> Result<Value, String> {
+pub fn map(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
     match args.as_slice() {
-        [Value::Func { params, body, env }, Value::List(items)] => {
-            let mut results = Vec::new();
-            for item in items {
-                let mut new_env = env.clone();
-                new_env.set(params[0].clone(), item.clone());
-                let result = eval_expr(&body, &mut new_env)?;
-                results.push(result);
-            }
-            Ok(Value::List(results))
-        }
-        [Value::BuiltinFunc {
-            name: _,
-            func,
-            args,
-        }, Value::List(items)] => {
-            let mut results = Vec::new();
+        [func, Value::List(items)] if func.is_function() => {
+            ctx.alloc_list(items.len())?;
+            let mut results = im_rc::Vector::new();
             for item in items {
-                let mut args = args.curried.clone();
-                args.push(item.clone());
-                let result = func(args)?;
-                results.push(result);
+                results.push_back(ctx.apply(func, vec![item.clone()])?);
             }
             Ok(Value::List(results))
         }
-        _ => Err(format!(
+        _ => Err(ctx.error(format!(
             "map: expected a function and a list, got {:?}",
             args
-        )),
+        ))),
     }
 }
 
-pub fn filter(args: Vec<Value>) -> Result<Value, String> {
+pub fn filter(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
     match args.as_slice() {
-        [Value::Func { params, body, env }, Value::List(items)] => {
-            let mut results = Vec::new();
+        [func, Value::List(items)] if func.is_function() => {
+            let mut results = im_rc::Vector::new();
             for item in items {
-                let mut new_env = env.clone();
-                new_env.set(params[0].clone(), item.clone());
-                let result = eval_expr(&body, &mut new_env)?;
-                if result == Value::Boolean(true) {
-                    results.push(item.clone());
+                if accepts(ctx, "filter", func, item)? {
+                    results.push_back(item.clone());
                 }
             }
+            ctx.alloc_list(results.len())?;
             Ok(Value::List(results))
         }
-        [Value::BuiltinFunc {
-            name: _,
-            func,
-            args,
-        }, Value::List(items)] => {
-            let mut results = Vec::new();
-            for item in items {
-                let mut args = args.curried.clone();
-                args.push(item.clone());
-                let result = func(args)?;
-                if result == Value::Boolean(true) {
-                    results.push(item.clone());
-                }
-            }
-            Ok(Value::List(results))
-        }
-        _ => Err("filter: expected a function and a list".to_string()),
+        _ => Err(ctx.error("filter: expected a function and a list")),
+    }
+}
+
+// Applies a predicate to an item. Like the condition of an `if`, its
+// answer must be a boolean.
+pub fn accepts(ctx: &Context, name: &str, func: &Value, item: &Value) -> Result<bool, Error> {
+    match ctx.apply(func, vec![item.clone()])? {
+        Value::Boolean(accepted) => Ok(accepted),
+        other => Err(ctx.error(format!(
+            "{}: the function must return a boolean, got {}",
+            name,
+            other.type_name()
+        ))),
     }
 }
 
-pub fn append(args: Vec<Value>) -> Result<Value, String> {
+pub fn append(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
     match args.as_slice() {
         [Value::List(list1), Value::List(list2)] => {
+            ctx.grow_list(list1.len() + list2.len(), 0)?;
             let mut new_list = list1.clone();
-            new_list.extend(list2.clone());
+            new_list.append(list2.clone());
             Ok(Value::List(new_list))
         }
         [Value::List(list1), item] => {
+            ctx.grow_list(list1.len() + 1, 1)?;
             let mut new_list = list1.clone();
-            new_list.push(item.clone());
+            new_list.push_back(item.clone());
             Ok(Value::List(new_list))
         }
-        _ => Err("append: expected two lists".to_string()),
+        _ => Err(ctx.error("append: expected two lists")),
     }
 }
 
-pub fn first(args: Vec<Value>) -> Result<Value, String> {
+pub fn first(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
     match args.as_slice() {
-        [Value::List(l)] => l.get(0).cloned().ok_or("first: list is empty".to_string()),
-        _ => Err("first: expected a list".to_string()),
+        [Value::List(l)] => l
+            .front()
+            .cloned()
+            .ok_or_else(|| ctx.error("first: list is empty")),
+        _ => Err(ctx.error("first: expected a list")),
     }
 }
 
-pub fn second(args: Vec<Value>) -> Result<Value, String> {
+pub fn first_opt(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
     match args.as_slice() {
-        [Value::List(l)] => l.get(1).cloned().ok_or("second: list is empty".to_string()),
-        _ => Err("second: expected a list".to_string()),
+        [Value::List(l)] => Ok(Value::Option(l.front().cloned().map(Box::new))),
+        _ => Err(ctx.error("first_opt: expected a list")),
     }
 }
 
-pub fn third(args: Vec<Value>) -> Result<Value, String> {
+pub fn get(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    let item = match args.as_slice() {
+        [Value::Int(i), Value::List(l)] => usize::try_from(*i).ok().and_then(|i| l.get(i)),
+        [Value::String(key), Value::Record(r)] => r.get(key),
+        _ => return Err(ctx.error("get: expected an index and a list, or a key and a record")),
+    };
+    Ok(Value::Option(item.cloned().map(Box::new)))
+}
+
+// Like `get`, with a default for missing elements and fields instead of an
+// option.
+pub fn get_or(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    let item =
+        match args.as_slice() {
+            [Value::Int(i), _, Value::List(l)] => usize::try_from(*i).ok().and_then(|i| l.get(i)),
+            [Value::String(key), _, Value::Record(r)] => r.get(key),
+            _ => return Err(ctx.error(
+                "get_or: expected an index, a default and a list, or a key, a default and a record",
+            )),
+        };
+    Ok(item.unwrap_or(&args[1]).clone())
+}
+
+pub fn second(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
     match args.as_slice() {
-        [Value::List(l)] => l.get(2).cloned().ok_or("third: list is empty".to_string()),
-        _ => Err("third: expected a list".to_string()),
+        [Value::List(l)] => l
+            .get(1)
+            .cloned()
+            .ok_or_else(|| ctx.error("second: list is empty")),
+        _ => Err(ctx.error("second: expected a list")),
     }
 }
 
-pub fn tail(args: Vec<Value>) -> Result<Value, String> {
+pub fn third(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [Value::List(l)] => l
+            .get(2)
+            .cloned()
+            .ok_or_else(|| ctx.error("third: list is empty")),
+        _ => Err(ctx.error("third: expected a list")),
+    }
+}
+
+pub fn tail(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
     match args.as_slice() {
         [Value::List(l)] => {
             if l.len() > 1 {
-                Ok(Value::List(l[1..].to_vec()))
+                Ok(Value::List(l.skip(1)))
             } else {
-                Ok(Value::List(vec![]))
+                Ok(Value::List(im_rc::Vector::new()))
             }
         }
-        _ => Err("rest: expected a list".to_string()),
+        _ => Err(ctx.error("rest: expected a list")),
     }
 }
 
-pub fn last(args: Vec<Value>) -> Result<Value, String> {
+pub fn last(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
     match args.as_slice() {
-        [Value::List(l)] => l.last().cloned().ok_or("last: list is empty".to_string()),
-        _ => Err("last: expected a list".to_string()),
+        [Value::List(l)] => l
+            .back()
+            .cloned()
+            .ok_or_else(|| ctx.error("last: list is empty")),
+        _ => Err(ctx.error("last: expected a list")),
     }
 }
 
-pub fn sum(args: Vec<Value>) -> Result<Value, String> {
+pub fn sum(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
     match args.as_slice() {
-        [Value::List(l)] => {
-            let sum: f64 = l
+        // Stays an integer as long as every element is one.
+        [Value::List(l)] => l.iter().try_fold(Value::Int(0), |acc, v| match v {
+            Value::Int(_) | Value::Number(_) | Value::Decimal(_) => {
+                ops::term(&TermOp::Plus, acc, v.clone())
+            }
+            _ => Err(ctx.error(format!(
+                "sum: expected a list of numbers, but got {}",
+                v.type_name()
+            ))),
+        }),
+        _ => Err(ctx.error("sum: expected a list")),
+    }
+}
+
+// Combines the items from the left, starting with the first one.
+pub fn reduce(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [func, Value::List(items)] if func.is_function() => {
+            let mut items = items.iter();
+            let first = items
+                .next()
+                .ok_or_else(|| ctx.error("reduce: list is empty"))?;
+            items.try_fold(first.clone(), |acc, item| {
+                ctx.apply(func, vec![acc, item.clone()])
+            })
+        }
+        _ => Err(ctx.error("reduce: expected a function and a list")),
+    }
+}
+
+pub fn fold(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [func, init, Value::List(items)] if func.is_function() => {
+            items.iter().try_fold(init.clone(), |acc, item| {
+                ctx.apply(func, vec![acc, item.clone()])
+            })
+        }
+        _ => Err(ctx.error("fold: expected a function, an initial value and a list")),
+    }
+}
+
+pub fn sort(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [Value::List(items)] => {
+            let keyed = items.iter().map(|item| (item.clone(), item.clone()));
+            sorted(ctx, "sort", keyed.collect())
+        }
+        _ => Err(ctx.error("sort: expected a list")),
+    }
+}
+
+pub fn sort_by(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [func, Value::List(items)] if func.is_function() => {
+            let mut keyed = Vec::with_capacity(items.len());
+            for item in items {
+                keyed.push((ctx.apply(func, vec![item.clone()])?, item.clone()));
+            }
+            sorted(ctx, "sort_by", keyed)
+        }
+        _ => Err(ctx.error("sort_by: expected a function and a list")),
+    }
+}
+
+// Sorts values by their keys, keeping the order of equal ones. The keys
+// are checked to be comparable first, so that the sort sees a total order.
+fn sorted(ctx: &Context, name: &str, mut keyed: Vec<(Value, Value)>) -> Result<Value, Error> {
+    ctx.alloc_list(keyed.len())?;
+    let mut kind = None;
+    for (i, (key, _)) in keyed.iter().enumerate() {
+        if !sortable(&mut kind, key) {
+            let earlier = keyed[..i].iter().map(|(key, _)| key);
+            let other = earlier
+                .clone()
+                .find(|other| ops::order(key, other).is_none())
+                .unwrap_or(&keyed[0].0);
+            return Err(ctx.error(incomparable(name, key, other)));
+        }
+    }
+    keyed.sort_by(|(a, _), (b, _)| ops::order(a, b).unwrap_or(Ordering::Equal));
+    Ok(Value::List(
+        keyed.into_iter().map(|(_, item)| item).collect(),
+    ))
+}
+
+// What the keys seen so far are, position by position for lists.
+// Floats and decimals can't be compared with each other, only with ints.
+enum Kind {
+    Number { floats: bool, decimals: bool },
+    String,
+    List(Vec<Option<Kind>>),
+}
+
+// Adds a key to the kind of the keys before it, false if it can't be
+// ordered against all of them.
+fn sortable(kind: &mut Option<Kind>, key: &Value) -> bool {
+    match (kind.as_mut(), key) {
+        (_, Value::Number(n)) if n.is_nan() => false,
+        (None, Value::Int(_) | Value::Number(_) | Value::Decimal(_)) => {
+            *kind = Some(Kind::Number {
+                floats: false,
+                decimals: false,
+            });
+            sortable(kind, key)
+        }
+        (None, Value::String(_)) => {
+            *kind = Some(Kind::String);
+            true
+        }
+        (None, Value::List(_)) => {
+            *kind = Some(Kind::List(Vec::new()));
+            sortable(kind, key)
+        }
+        (Some(Kind::Number { .. }), Value::Int(_)) => true,
+        (Some(Kind::Number { floats, decimals }), Value::Number(_)) => {
+            *floats = true;
+            !*decimals
+        }
+        (Some(Kind::Number { floats, decimals }), Value::Decimal(_)) => {
+            *decimals = true;
+            !*floats
+        }
+        (Some(Kind::String), Value::String(_)) => true,
+        (Some(Kind::List(kinds)), Value::List(items)) => {
+            for (i, item) in items.iter().enumerate() {
+                if i == kinds.len() {
+                    kinds.push(None);
+                }
+                if !sortable(&mut kinds[i], item) {
+                    return false;
+                }
+            }
+            true
+        }
+        _ => false,
+    }
+}
+
+fn incomparable(name: &str, a: &Value, b: &Value) -> String {
+    format!(
+        "{}: cannot compare {} and {}",
+        name,
+        a.type_name(),
+        b.type_name()
+    )
+}
+
+pub fn reverse(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [Value::List(items)] => {
+            ctx.alloc_list(items.len())?;
+            Ok(Value::List(items.iter().rev().cloned().collect()))
+        }
+        _ => Err(ctx.error("reverse: expected a list")),
+    }
+}
+
+// Keeps the first of equal items, equal as with `is`.
+pub fn unique(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [Value::List(items)] => {
+            let mut results = im_rc::Vector::new();
+            for item in items {
+                let mut seen = false;
+                for result in results.iter() {
+                    if ops::equals(result, item)? {
+                        seen = true;
+                        break;
+                    }
+                }
+                if !seen {
+                    results.push_back(item.clone());
+                }
+            }
+            ctx.alloc_list(results.len())?;
+            Ok(Value::List(results))
+        }
+        _ => Err(ctx.error("unique: expected a list")),
+    }
+}
+
+// Groups items into a record by the key the function gives them. Keys
+// that aren't strings are written the way `print` writes them.
+pub fn group_by(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [func, Value::List(items)] if func.is_function() => {
+            let mut groups: im_rc::OrdMap<String, Value> = im_rc::OrdMap::new();
+            for item in items {
+                let key = match ctx.apply(func, vec![item.clone()])? {
+                    Value::String(key) => key,
+                    key => format_value(&key),
+                };
+                match groups.get_mut(&key) {
+                    Some(Value::List(group)) => group.push_back(item.clone()),
+                    _ => {
+                        groups.insert(key, Value::List(im_rc::vector![item.clone()]));
+                    }
+                }
+            }
+            ctx.alloc(groups.len() + items.len())?;
+            Ok(Value::Record(groups))
+        }
+        _ => Err(ctx.error("group_by: expected a function and a list")),
+    }
+}
+
+// Pairs up the items of two lists, as far as the shorter one goes.
+pub fn zip(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [Value::List(left), Value::List(right)] => {
+            let len = left.len().min(right.len());
+            ctx.alloc_list(len)?;
+            ctx.alloc(len * 2)?;
+            let pairs = left
                 .iter()
-                .filter_map(|v| match v {
-                    Value::Number(n) => Some(*n),
-                    _ => None,
-                })
-                .sum();
-            Ok(Value::Number(sum))
-        }
-        _ => Err("sum: expected a list".to_string()),
+                .zip(right)
+                .map(|(l, r)| Value::List(im_rc::vector![l.clone(), r.clone()]));
+            Ok(Value::List(pairs.collect()))
+        }
+        _ => Err(ctx.error("zip: expected two lists")),
+    }
+}
+
+pub fn flat_map(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [func, Value::List(items)] if func.is_function() => {
+            let mut results = im_rc::Vector::new();
+            for item in items {
+                match ctx.apply(func, vec![item.clone()])? {
+                    Value::List(mapped) => results.append(mapped),
+                    other => {
+                        return Err(ctx.error(format!(
+                            "flat_map: the function must return a list, got {}",
+                            other.type_name()
+                        )))
+                    }
+                }
+            }
+            ctx.alloc_list(results.len())?;
+            Ok(Value::List(results))
+        }
+        _ => Err(ctx.error("flat_map: expected a function and a list")),
+    }
+}
+
+pub fn take(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [Value::Int(n), Value::List(items)] => {
+            let n = count_arg(ctx, "take", *n)?.min(items.len());
+            Ok(Value::List(items.take(n)))
+        }
+        _ => Err(ctx.error("take: expected a count and a list")),
+    }
+}
+
+pub fn drop(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [Value::Int(n), Value::List(items)] => {
+            let n = count_arg(ctx, "drop", *n)?.min(items.len());
+            Ok(Value::List(items.skip(n)))
+        }
+        _ => Err(ctx.error("drop: expected a count and a list")),
+    }
+}
+
+fn count_arg(ctx: &Context, name: &str, n: i64) -> Result<usize, Error> {
+    usize::try_from(n).map_err(|_| ctx.error(format!("{}: negative count: {}", name, n)))
+}
+
+pub fn any(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [func, Value::List(items)] if func.is_function() => {
+            for item in items {
+                if accepts(ctx, "any", func, item)? {
+                    return Ok(Value::Boolean(true));
+                }
+            }
+            Ok(Value::Boolean(false))
+        }
+        _ => Err(ctx.error("any: expected a function and a list")),
+    }
+}
+
+pub fn all(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [func, Value::List(items)] if func.is_function() => {
+            for item in items {
+                if !accepts(ctx, "all", func, item)? {
+                    return Ok(Value::Boolean(false));
+                }
+            }
+            Ok(Value::Boolean(true))
+        }
+        _ => Err(ctx.error("all: expected a function and a list")),
+    }
+}
+
+pub fn count(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [func, Value::List(items)] if func.is_function() => {
+            let mut count = 0;
+            for item in items {
+                if accepts(ctx, "count", func, item)? {
+                    count += 1;
+                }
+            }
+            Ok(Value::Int(count))
+        }
+        _ => Err(ctx.error("count: expected a function and a list")),
+    }
+}
+
+pub fn enumerate(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [Value::List(items)] => {
+            ctx.alloc_list(items.len())?;
+            ctx.alloc(items.len() * 2)?;
+            let pairs = items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| Value::List(im_rc::vector![Value::Int(i as i64), item.clone()]));
+            Ok(Value::List(pairs.collect()))
+        }
+        _ => Err(ctx.error("enumerate: expected a list")),
+    }
+}
+
+// Splits a list into the items the function accepts and the others.
+pub fn partition(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [func, Value::List(items)] if func.is_function() => {
+            let mut accepted = im_rc::Vector::new();
+            let mut rejected = im_rc::Vector::new();
+            for item in items {
+                if accepts(ctx, "partition", func, item)? {
+                    accepted.push_back(item.clone());
+                } else {
+                    rejected.push_back(item.clone());
+                }
+            }
+            ctx.alloc_list(items.len() + 2)?;
+            Ok(Value::List(im_rc::vector![
+                Value::List(accepted),
+                Value::List(rejected)
+            ]))
+        }
+        _ => Err(ctx.error("partition: expected a function and a list")),
+    }
+}
+
+// Cuts a list into lists of `size` items, the last one possibly shorter.
+pub fn chunk(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [Value::Int(size), Value::List(items)] => {
+            let size = usize::try_from(*size)
+                .ok()
+                .filter(|size| *size > 0)
+                .ok_or_else(|| ctx.error(format!("chunk: size must be positive, got {}", size)))?;
+            let mut rest = items.clone();
+            let mut chunks = im_rc::Vector::new();
+            while !rest.is_empty() {
+                let tail = rest.split_off(size.min(rest.len()));
+                chunks.push_back(Value::List(rest));
+                rest = tail;
+            }
+            ctx.alloc_list(chunks.len())?;
+            ctx.alloc(items.len())?;
+            Ok(Value::List(chunks))
+        }
+        _ => Err(ctx.error("chunk: expected a size and a list")),
+    }
+}
+
+pub fn min(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    extreme(ctx, "min", Ordering::Less, args)
+}
+
+pub fn max(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    extreme(ctx, "max", Ordering::Greater, args)
+}
+
+// The first item that no other item is `wanted` of, in the order of `sort`.
+fn extreme(ctx: &Context, name: &str, wanted: Ordering, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [Value::List(items)] => {
+            let mut items = items.iter();
+            let mut best = items
+                .next()
+                .ok_or_else(|| ctx.error(format!("{}: list is empty", name)))?;
+            for item in items {
+                match ops::order(item, best) {
+                    Some(ordering) if ordering == wanted => best = item,
+                    Some(_) => {}
+                    None => return Err(ctx.error(incomparable(name, item, best))),
+                }
+            }
+            Ok(best.clone())
+        }
+        _ => Err(ctx.error(format!("{}: expected a list", name))),
     }
 }
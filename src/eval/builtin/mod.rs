@@ -1,7 +1,11 @@
 pub mod builtin;
+pub mod error;
 pub mod etc;
 pub mod file;
 pub mod io;
 pub mod list;
 pub mod num;
+pub mod option;
+pub mod record;
+pub mod regex;
 pub mod str;
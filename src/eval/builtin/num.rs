@@ -1,8 +1,89 @@
+use std::str::FromStr;
+
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::{Decimal, RoundingStrategy};
+
+use crate::eval::context::Context;
+use crate::eval::error::Error;
 use crate::eval::value::Value;
 
-pub fn str(args: Vec<Value>) -> Result<Value, String> {
+pub fn str(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
     match args.as_slice() {
-        [Value::Number(n)] => Ok(Value::String(n.to_string())),
-        _ => Err("str: expected a number".to_string()),
+        [Value::Int(n)] => Ok(Value::String(n.to_string())),
+        [Value::Number(n)] => Ok(Value::String(format!("{:?}", n))),
+        [Value::Decimal(d)] => Ok(Value::String(d.to_string())),
+        _ => Err(ctx.error("str: expected a number")),
+    }
+}
+
+pub fn decimal(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [Value::String(s)] => match Decimal::from_str(s.trim()) {
+            Ok(d) => Ok(Value::Decimal(d)),
+            Err(_) => Err(ctx.error(format!("decimal: invalid decimal: {}", s))),
+        },
+        [Value::Int(n)] => Ok(Value::Decimal(Decimal::from(*n))),
+        // Uses the shortest representation that reads back as the same
+        // float, so `decimal(0.1)` is 0.1 and not 0.1000000000000000055...
+        [Value::Number(n)] => match Decimal::from_str(&format!("{:?}", n)) {
+            Ok(d) => Ok(Value::Decimal(d)),
+            Err(_) => Err(ctx.error(format!("decimal: {:?} is out of decimal range", n))),
+        },
+        [Value::Decimal(d)] => Ok(Value::Decimal(*d)),
+        _ => Err(ctx.error("decimal: expected a string or number")),
+    }
+}
+
+pub fn round(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [places, value] => round_number(ctx, RoundingStrategy::MidpointAwayFromZero, places, value),
+        _ => Err(ctx.error("round: expected the number of places and a number")),
+    }
+}
+
+pub fn round_with(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [Value::String(mode), places, value] => {
+            let strategy = match mode.as_str() {
+                "half_up" => RoundingStrategy::MidpointAwayFromZero,
+                "half_down" => RoundingStrategy::MidpointTowardZero,
+                "half_even" => RoundingStrategy::MidpointNearestEven,
+                "up" => RoundingStrategy::AwayFromZero,
+                "down" => RoundingStrategy::ToZero,
+                "ceil" => RoundingStrategy::ToPositiveInfinity,
+                "floor" => RoundingStrategy::ToNegativeInfinity,
+                _ => return Err(ctx.error(format!("round_with: unknown rounding mode: {}", mode))),
+            };
+            round_number(ctx, strategy, places, value)
+        }
+        _ => Err(ctx.error("round_with: expected a mode, the number of places and a number")),
+    }
+}
+
+fn round_number(
+    ctx: &Context,
+    strategy: RoundingStrategy,
+    places: &Value,
+    value: &Value,
+) -> Result<Value, Error> {
+    let places = match places {
+        Value::Int(n) => u32::try_from(*n)
+            .map_err(|_| ctx.error(format!("round: invalid number of places: {}", n)))?,
+        _ => return Err(ctx.error("round: the number of places must be an integer")),
+    };
+    match value {
+        Value::Int(n) => Ok(Value::Int(*n)),
+        Value::Decimal(d) => Ok(Value::Decimal(d.round_dp_with_strategy(places, strategy))),
+        // Floats are rounded through their decimal representation, so that
+        // 2.675 rounds to 2.68 like it reads rather than to 2.67.
+        Value::Number(_) => match decimal(ctx, vec![value.clone()])? {
+            Value::Decimal(d) => d
+                .round_dp_with_strategy(places, strategy)
+                .to_f64()
+                .map(Value::Number)
+                .ok_or_else(|| ctx.error("round: result is out of float range")),
+            _ => unreachable!(),
+        },
+        _ => Err(ctx.error("round: expected a number")),
     }
 }
@@ -0,0 +1,24 @@
+use crate::eval::context::Context;
+use crate::eval::error::Error;
+use crate::eval::value::Value;
+
+pub fn some(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [value] => Ok(Value::Option(Some(Box::new(value.clone())))),
+        _ => Err(ctx.error("some: expected one argument")),
+    }
+}
+
+pub fn ok(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [value] => Ok(Value::Result(Ok(Box::new(value.clone())))),
+        _ => Err(ctx.error("ok: expected one argument")),
+    }
+}
+
+pub fn err(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [value] => Ok(Value::Result(Err(Box::new(value.clone())))),
+        _ => Err(ctx.error("err: expected one argument")),
+    }
+}
@@ -0,0 +1,127 @@
+use crate::eval::context::Context;
+use crate::eval::error::Error;
+use crate::eval::value::Value;
+
+pub fn keys(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [Value::Record(r)] => {
+            ctx.alloc_list(r.len())?;
+            Ok(Value::List(r.keys().cloned().map(Value::String).collect()))
+        }
+        _ => Err(ctx.error("keys: expected a record")),
+    }
+}
+
+pub fn values(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [Value::Record(r)] => {
+            ctx.alloc_list(r.len())?;
+            Ok(Value::List(r.values().cloned().collect()))
+        }
+        _ => Err(ctx.error("values: expected a record")),
+    }
+}
+
+// The fields of a record as [key, value] pairs.
+pub fn entries(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [Value::Record(r)] => {
+            ctx.alloc_list(r.len())?;
+            ctx.alloc(r.len() * 2)?;
+            Ok(Value::List(
+                r.iter()
+                    .map(|(key, value)| {
+                        Value::List(im_rc::vector![Value::String(key.clone()), value.clone()])
+                    })
+                    .collect(),
+            ))
+        }
+        _ => Err(ctx.error("entries: expected a record")),
+    }
+}
+
+// Builds a record from [key, value] pairs, later pairs overriding earlier
+// ones with the same key.
+pub fn from_entries(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [Value::List(pairs)] => {
+            ctx.alloc(pairs.len())?;
+            let mut record = im_rc::OrdMap::new();
+            for pair in pairs {
+                match pair {
+                    Value::List(pair) if pair.len() == 2 => match &pair[0] {
+                        Value::String(key) => {
+                            record.insert(key.clone(), pair[1].clone());
+                        }
+                        key => {
+                            return Err(ctx.error(format!(
+                                "from_entries: keys must be strings, but got {}",
+                                key.type_name()
+                            )))
+                        }
+                    },
+                    _ => {
+                        return Err(ctx.error("from_entries: expected a list of [key, value] pairs"))
+                    }
+                }
+            }
+            Ok(Value::Record(record))
+        }
+        _ => Err(ctx.error("from_entries: expected a list of [key, value] pairs")),
+    }
+}
+
+pub fn has(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [Value::String(key), Value::Record(r)] => Ok(Value::Boolean(r.contains_key(key))),
+        _ => Err(ctx.error("has: expected a key and a record")),
+    }
+}
+
+// Sets a field, adding it when the record doesn't have it yet.
+pub fn set(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [Value::String(key), value, Value::Record(r)] => {
+            ctx.alloc(1)?;
+            Ok(Value::Record(r.update(key.clone(), value.clone())))
+        }
+        _ => Err(ctx.error("set: expected a key, a value and a record")),
+    }
+}
+
+pub fn remove(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [Value::String(key), Value::Record(r)] => Ok(Value::Record(r.without(key))),
+        _ => Err(ctx.error("remove: expected a key and a record")),
+    }
+}
+
+// Adds the fields of the first record to the second, overriding the fields
+// they have in common.
+pub fn merge(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [Value::Record(updates), Value::Record(r)] => {
+            ctx.alloc(updates.len())?;
+            let mut record = r.clone();
+            for (key, value) in updates {
+                record.insert(key.clone(), value.clone());
+            }
+            Ok(Value::Record(record))
+        }
+        _ => Err(ctx.error("merge: expected two records")),
+    }
+}
+
+pub fn map_values(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [func, Value::Record(r)] if func.is_function() => {
+            ctx.alloc(r.len())?;
+            let mut record = im_rc::OrdMap::new();
+            for (key, value) in r {
+                record.insert(key.clone(), ctx.apply(func, vec![value.clone()])?);
+            }
+            Ok(Value::Record(record))
+        }
+        _ => Err(ctx.error("map_values: expected a function and a record")),
+    }
+}
@@ -0,0 +1,91 @@
+use std::borrow::Cow;
+
+use crate::eval::context::Context;
+use crate::eval::error::Error;
+use crate::eval::value::{Regex, Value};
+
+pub fn regex(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [Value::String(pattern)] => Ok(Value::Regex(Regex(compile(ctx, "regex", pattern)?))),
+        _ => Err(ctx.error("regex: expected a pattern string")),
+    }
+}
+
+pub fn matches(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [pattern, Value::String(s)] => {
+            let regex = to_regex(ctx, "matches", pattern)?;
+            Ok(Value::Boolean(regex.is_match(s)))
+        }
+        _ => Err(ctx.error("matches: expected a regex and a string")),
+    }
+}
+
+pub fn find_all(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [pattern, Value::String(s)] => {
+            let regex = to_regex(ctx, "find_all", pattern)?;
+            let found: Vec<&str> = regex.find_iter(s).map(|m| m.as_str()).collect();
+            ctx.alloc_list(found.len())?;
+            Ok(Value::List(found.into_iter().map(Value::from).collect()))
+        }
+        _ => Err(ctx.error("find_all: expected a regex and a string")),
+    }
+}
+
+// The named groups of the first match, as a record. Groups that took no
+// part in the match are empty strings, so that every field exists.
+pub fn captures(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [pattern, Value::String(s)] => {
+            let regex = to_regex(ctx, "captures", pattern)?;
+            let Some(captures) = regex.captures(s) else {
+                return Ok(Value::Option(None));
+            };
+            let names: Vec<&str> = regex.capture_names().flatten().collect();
+            ctx.alloc(names.len())?;
+            let record = names
+                .into_iter()
+                .map(|name| {
+                    let text = captures.name(name).map_or("", |m| m.as_str());
+                    (name.to_string(), Value::from(text))
+                })
+                .collect();
+            Ok(Value::Option(Some(Box::new(Value::Record(record)))))
+        }
+        _ => Err(ctx.error("captures: expected a regex and a string")),
+    }
+}
+
+pub fn replace_all(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [pattern, Value::String(replacement), Value::String(s)] => {
+            let regex = to_regex(ctx, "replace_all", pattern)?;
+            let replaced = regex.replace_all(s, replacement.as_str());
+            Ok(Value::String(replaced.into_owned()))
+        }
+        _ => Err(ctx.error("replace_all: expected a regex and two strings")),
+    }
+}
+
+// Regexes can be given as pattern strings too, compiled for the call.
+fn to_regex<'a>(
+    ctx: &Context,
+    name: &str,
+    pattern: &'a Value,
+) -> Result<Cow<'a, regex::Regex>, Error> {
+    match pattern {
+        Value::Regex(regex) => Ok(Cow::Borrowed(&regex.0)),
+        Value::String(pattern) => Ok(Cow::Owned(compile(ctx, name, pattern)?)),
+        _ => Err(ctx.error(format!(
+            "{}: expected a regex or a pattern string, got {}",
+            name,
+            pattern.type_name()
+        ))),
+    }
+}
+
+fn compile(ctx: &Context, name: &str, pattern: &str) -> Result<regex::Regex, Error> {
+    regex::Regex::new(pattern)
+        .map_err(|error| ctx.error(format!("{}: invalid regex: {}", name, error)))
+}
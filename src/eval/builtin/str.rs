@@ -1,21 +1,269 @@
+use rust_decimal::prelude::ToPrimitive;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::eval::builtin::io::format_value;
+use crate::eval::context::Context;
+use crate::eval::error::Error;
 use crate::eval::value::Value;
 
-pub fn int(args: Vec<Value>) -> Result<Value, String> {
+pub fn int(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [Value::String(s)] => match s.trim().parse::<i64>() {
+            Ok(n) => Ok(Value::Int(n)),
+            Err(_) => Err(ctx.error(format!("int: invalid integer: {}", s))),
+        },
+        [Value::Int(n)] => Ok(Value::Int(*n)),
+        // Truncates toward zero, like `as` but refusing values that don't fit.
+        [Value::Number(n)] => {
+            if n.is_finite() && *n >= i64::MIN as f64 && *n < i64::MAX as f64 {
+                Ok(Value::Int(n.trunc() as i64))
+            } else {
+                Err(ctx.error(format!("int: {:?} is out of integer range", n)))
+            }
+        }
+        [Value::Decimal(d)] => d
+            .trunc()
+            .to_i64()
+            .map(Value::Int)
+            .ok_or_else(|| ctx.error(format!("int: {} is out of integer range", d))),
+        _ => Err(ctx.error("int: expected a string or number")),
+    }
+}
+
+pub fn parse_int(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [Value::String(s)] => Ok(Value::Result(match s.trim().parse::<i64>() {
+            Ok(n) => Ok(Box::new(Value::Int(n))),
+            Err(_) => Err(Box::new(Value::String(format!("invalid integer: {}", s)))),
+        })),
+        _ => Err(ctx.error("parse_int: expected a string")),
+    }
+}
+
+pub fn float(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
     match args.as_slice() {
-        [Value::String(s)] => match s.parse::<f64>() {
+        [Value::String(s)] => match s.trim().parse::<f64>() {
             Ok(n) => Ok(Value::Number(n)),
-            Err(_) => Err("int: invalid number".to_string()),
+            Err(_) => Err(ctx.error(format!("float: invalid number: {}", s))),
         },
-        _ => Err("int: expected a number".to_string()),
+        [Value::Int(n)] => Ok(Value::Number(*n as f64)),
+        [Value::Number(n)] => Ok(Value::Number(*n)),
+        [Value::Decimal(d)] => d
+            .to_f64()
+            .map(Value::Number)
+            .ok_or_else(|| ctx.error(format!("float: {} is out of float range", d))),
+        _ => Err(ctx.error("float: expected a string or number")),
     }
 }
 
-pub fn split(args: Vec<Value>) -> Result<Value, String> {
+pub fn split(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
     match args.as_slice() {
         [Value::String(delim), Value::String(s)] => {
             let parts: Vec<String> = s.split(delim).map(|s| s.to_string()).collect();
+            ctx.alloc_list(parts.len())?;
             Ok(Value::List(parts.into_iter().map(Value::String).collect()))
         }
-        _ => Err("split: expected two strings".to_string()),
+        _ => Err(ctx.error("split: expected two strings")),
+    }
+}
+
+// Splits a string into what readers see as characters, e.g. a letter with
+// its accents or an emoji sequence, where indexing goes by code points.
+pub fn graphemes(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [Value::String(s)] => {
+            let graphemes: Vec<&str> = s.graphemes(true).collect();
+            ctx.alloc_list(graphemes.len())?;
+            Ok(Value::List(
+                graphemes.into_iter().map(Value::from).collect(),
+            ))
+        }
+        _ => Err(ctx.error("graphemes: expected a string")),
+    }
+}
+
+pub fn trim(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [Value::String(s)] => Ok(Value::String(s.trim().to_string())),
+        _ => Err(ctx.error("trim: expected a string")),
+    }
+}
+
+pub fn lower(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [Value::String(s)] => Ok(Value::String(s.to_lowercase())),
+        _ => Err(ctx.error("lower: expected a string")),
+    }
+}
+
+pub fn upper(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [Value::String(s)] => Ok(Value::String(s.to_uppercase())),
+        _ => Err(ctx.error("upper: expected a string")),
+    }
+}
+
+pub fn replace(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [Value::String(from), Value::String(to), Value::String(s)] => {
+            Ok(Value::String(s.replace(from.as_str(), to)))
+        }
+        _ => Err(ctx.error("replace: expected three strings")),
+    }
+}
+
+pub fn starts_with(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [Value::String(prefix), Value::String(s)] => {
+            Ok(Value::Boolean(s.starts_with(prefix.as_str())))
+        }
+        _ => Err(ctx.error("starts_with: expected two strings")),
+    }
+}
+
+pub fn ends_with(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [Value::String(suffix), Value::String(s)] => {
+            Ok(Value::Boolean(s.ends_with(suffix.as_str())))
+        }
+        _ => Err(ctx.error("ends_with: expected two strings")),
+    }
+}
+
+// Items that aren't strings are written the way `print` writes them.
+pub fn join(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [Value::String(sep), Value::List(items)] => {
+            let items: Vec<String> = items.iter().map(format_value).collect();
+            Ok(Value::String(items.join(sep)))
+        }
+        _ => Err(ctx.error("join: expected a separator and a list")),
+    }
+}
+
+pub fn pad_left(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    let (padding, s) = padding("pad_left", ctx, &args)?;
+    Ok(Value::String(padding + s))
+}
+
+pub fn pad_right(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    let (padding, s) = padding("pad_right", ctx, &args)?;
+    Ok(Value::String(s.to_string() + &padding))
+}
+
+// The fill characters that bring a string to a width, counted in
+// characters. Strings already as wide are left alone.
+fn padding<'a>(name: &str, ctx: &Context, args: &'a [Value]) -> Result<(String, &'a str), Error> {
+    match args {
+        [Value::Int(width), Value::String(fill), Value::String(s)] => {
+            let mut chars = fill.chars();
+            let (Some(fill), None) = (chars.next(), chars.next()) else {
+                return Err(ctx.error(format!("{}: the fill must be a single character", name)));
+            };
+            let width = usize::try_from(*width).unwrap_or(0);
+            let missing = width.saturating_sub(s.chars().count());
+            let len = missing
+                .saturating_mul(fill.len_utf8())
+                .saturating_add(s.len());
+            ctx.alloc_string(len)?;
+            Ok((std::iter::repeat_n(fill, missing).collect(), s))
+        }
+        _ => Err(ctx.error(format!(
+            "{}: expected a width, a fill character and a string",
+            name
+        ))),
+    }
+}
+
+pub fn lines(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [Value::String(s)] => {
+            let lines: Vec<&str> = s.lines().collect();
+            ctx.alloc_list(lines.len())?;
+            Ok(Value::List(lines.into_iter().map(Value::from).collect()))
+        }
+        _ => Err(ctx.error("lines: expected a string")),
+    }
+}
+
+pub fn chars(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [Value::String(s)] => {
+            ctx.alloc_list(s.chars().count())?;
+            Ok(Value::List(
+                s.chars().map(|c| Value::String(c.to_string())).collect(),
+            ))
+        }
+        _ => Err(ctx.error("chars: expected a string")),
+    }
+}
+
+pub fn repeat(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [Value::Int(n), Value::String(s)] => {
+            let n = usize::try_from(*n)
+                .map_err(|_| ctx.error(format!("repeat: negative count: {}", n)))?;
+            ctx.alloc_string(s.len().saturating_mul(n))?;
+            Ok(Value::String(s.repeat(n)))
+        }
+        _ => Err(ctx.error("repeat: expected a count and a string")),
+    }
+}
+
+pub fn format(ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+    match args.as_slice() {
+        [Value::String(template), values @ (Value::List(_) | Value::Record(_))] => {
+            fill(template, values)
+                .map(Value::String)
+                .map_err(|message| ctx.error(format!("format: {}", message)))
+        }
+        _ => Err(ctx.error("format: expected a template and a list or record")),
+    }
+}
+
+// Replaces the placeholders of a `format` template: `{}` takes the next
+// list item, `{0}` the item at an index and `{name}` a record field. `{{`
+// and `}}` stand for braces.
+fn fill(template: &str, values: &Value) -> Result<String, String> {
+    let mut result = String::new();
+    let mut next = 0;
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                result.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                result.push('}');
+            }
+            '{' => {
+                let mut key = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => key.push(c),
+                        None => return Err("unclosed '{' in template".into()),
+                    }
+                }
+                let value = match (values, key.trim()) {
+                    (Value::List(items), "") => {
+                        next += 1;
+                        items.get(next - 1)
+                    }
+                    (Value::List(items), key) => key.parse().ok().and_then(|i: usize| items.get(i)),
+                    (Value::Record(fields), key) => fields.get(key),
+                    _ => None,
+                };
+                match value {
+                    Some(value) => result.push_str(&format_value(value)),
+                    None => return Err(format!("no value for {{{}}}", key)),
+                }
+            }
+            '}' => return Err("unmatched '}' in template".into()),
+            _ => result.push(c),
+        }
     }
+    Ok(result)
 }
@@ -0,0 +1,178 @@
+use std::cell::{RefCell, RefMut};
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::rc::Rc;
+
+use crate::eval::error::{Error, ErrorKind};
+use crate::eval::eval::apply;
+use crate::eval::limits::{Budget, Limits};
+use crate::eval::sandbox::Sandbox;
+use crate::eval::value::{NativeFn, Value};
+use crate::token::Range;
+
+// The streams builtins read from and write to, the sandbox limiting what
+// else they may touch and the budget of the run. Clones share them, so
+// output written by functions called back from builtins ends up in the
+// same place as the rest and counts against the same limits.
+#[derive(Clone)]
+pub struct Io {
+    pub stdout: Rc<RefCell<dyn Write>>,
+    pub stdin: Rc<RefCell<dyn BufRead>>,
+    pub sandbox: Rc<Sandbox>,
+    pub budget: Rc<Budget>,
+}
+
+impl Io {
+    pub fn new(stdout: impl Write + 'static, stdin: impl BufRead + 'static) -> Self {
+        Io {
+            stdout: Rc::new(RefCell::new(stdout)),
+            stdin: Rc::new(RefCell::new(stdin)),
+            sandbox: Rc::new(Sandbox::unrestricted()),
+            budget: Rc::new(Budget::default()),
+        }
+    }
+
+    pub fn with_sandbox(self, sandbox: Sandbox) -> Self {
+        Io {
+            sandbox: Rc::new(sandbox),
+            ..self
+        }
+    }
+
+    pub fn with_limits(self, limits: Limits) -> Self {
+        Io {
+            budget: Rc::new(Budget::new(limits)),
+            ..self
+        }
+    }
+}
+
+impl Default for Io {
+    fn default() -> Self {
+        Io::new(io::stdout(), BufReader::new(io::stdin()))
+    }
+}
+
+impl fmt::Debug for Io {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Io")
+    }
+}
+
+impl PartialEq for Io {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.stdout, &other.stdout)
+            && Rc::ptr_eq(&self.stdin, &other.stdin)
+            && self.sandbox == other.sandbox
+            && Rc::ptr_eq(&self.budget, &other.budget)
+    }
+}
+
+// What a builtin gets from the interpreter running it, whichever engine
+// that is.
+pub struct Context<'a> {
+    io: &'a Io,
+    span: Option<&'a Range>, // the call being run, if it was written as one
+}
+
+impl<'a> Context<'a> {
+    pub fn new(io: &'a Io, span: Option<&'a Range>) -> Self {
+        Context { io, span }
+    }
+
+    pub fn io(&self) -> &'a Io {
+        self.io
+    }
+
+    pub fn stdout(&self) -> RefMut<'a, dyn Write> {
+        self.io.stdout.borrow_mut()
+    }
+
+    pub fn stdin(&self) -> RefMut<'a, dyn BufRead> {
+        self.io.stdin.borrow_mut()
+    }
+
+    // Permission errors for what the sandbox doesn't allow, to check before
+    // touching files or the environment.
+    pub fn check_read(&self, path: &str) -> Result<(), Error> {
+        self.permit(self.io.sandbox.check_read(path))
+    }
+
+    pub fn check_write(&self, path: &str) -> Result<(), Error> {
+        self.permit(self.io.sandbox.check_write(path))
+    }
+
+    pub fn check_env(&self, name: &str) -> Result<(), Error> {
+        self.permit(self.io.sandbox.check_env(name))
+    }
+
+    fn permit(&self, check: Result<(), String>) -> Result<(), Error> {
+        check.map_err(|message| self.raise(ErrorKind::Permission, message))
+    }
+
+    // Accounts for a list of `len` elements the builtin is about to build.
+    pub fn alloc_list(&self, len: usize) -> Result<(), Error> {
+        self.io.budget.list(len).map_err(|error| self.locate(error))
+    }
+
+    // Checks the length of a list the builtin is about to build from an
+    // existing one, charging only the `created` elements it adds.
+    pub fn grow_list(&self, len: usize, created: usize) -> Result<(), Error> {
+        self.io
+            .budget
+            .list_len(len)
+            .and_then(|_| self.io.budget.alloc(created))
+            .map_err(|error| self.locate(error))
+    }
+
+    // Accounts for `count` record fields the builtin is about to create.
+    pub fn alloc(&self, count: usize) -> Result<(), Error> {
+        self.io
+            .budget
+            .alloc(count)
+            .map_err(|error| self.locate(error))
+    }
+
+    // Checks a string of `len` bytes the builtin is about to build.
+    pub fn alloc_string(&self, len: usize) -> Result<(), Error> {
+        self.io
+            .budget
+            .string(len)
+            .map_err(|error| self.locate(error))
+    }
+
+    // An error of the builtin, located at its call.
+    pub fn error(&self, message: impl Into<String>) -> Error {
+        self.raise(ErrorKind::Builtin, message)
+    }
+
+    pub fn raise(&self, kind: ErrorKind, message: impl Into<String>) -> Error {
+        self.locate(Error::new(kind, message))
+    }
+
+    fn locate(&self, error: Error) -> Error {
+        match self.span {
+            Some(span) => error.with_span(span),
+            None => error,
+        }
+    }
+
+    // Applies a function value of either engine to `args`, the way a call
+    // would. Calls back into the script are located at the builtin's call.
+    pub fn apply(&self, func: &Value, args: Vec<Value>) -> Result<Value, Error> {
+        apply(func.clone(), args, self.io, self.span)
+    }
+}
+
+// Runs a fully applied builtin. `span` is the call, when it was written as
+// one.
+pub fn call_builtin(
+    name: &str,
+    func: &NativeFn,
+    args: Vec<Value>,
+    io: &Io,
+    span: Option<&Range>,
+) -> Result<Value, Error> {
+    func.call(&Context::new(io, span), args)
+        .map_err(|e| e.in_call(name, span))
+}
@@ -0,0 +1,321 @@
+use std::collections::HashMap;
+
+use rust_decimal::Decimal;
+
+use crate::eval::error::Error;
+use crate::eval::value::Value;
+
+// Conversions between Rust types and Dolang values, for applications
+// passing data in and out of scripts.
+
+// Rust to Dolang
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::Int(value)
+    }
+}
+
+impl From<i32> for Value {
+    fn from(value: i32) -> Self {
+        Value::Int(value.into())
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Number(value)
+    }
+}
+
+impl From<Decimal> for Value {
+    fn from(value: Decimal) -> Self {
+        Value::Decimal(value)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Boolean(value)
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::String(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::String(value.to_string())
+    }
+}
+
+impl From<()> for Value {
+    fn from(_: ()) -> Self {
+        Value::Unit
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(values: Vec<T>) -> Self {
+        Value::List(values.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        Value::Option(value.map(|value| Box::new(value.into())))
+    }
+}
+
+impl<T: Into<Value>, E: Into<Value>> From<Result<T, E>> for Value {
+    fn from(value: Result<T, E>) -> Self {
+        Value::Result(
+            value
+                .map(|value| Box::new(value.into()))
+                .map_err(|error| Box::new(error.into())),
+        )
+    }
+}
+
+impl<T: Into<Value>> From<HashMap<String, T>> for Value {
+    fn from(fields: HashMap<String, T>) -> Self {
+        Value::Record(
+            fields
+                .into_iter()
+                .map(|(key, value)| (key, value.into()))
+                .collect(),
+        )
+    }
+}
+
+// Dolang to Rust
+
+fn expected(type_name: &str, value: &Value) -> Error {
+    format!("Expected {}, but got {}", type_name, value.type_name()).into()
+}
+
+impl TryFrom<Value> for i64 {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self, Error> {
+        match value {
+            Value::Int(n) => Ok(n),
+            _ => Err(expected("int", &value)),
+        }
+    }
+}
+
+// Integers widen to floats and decimals, as they do in arithmetic.
+impl TryFrom<Value> for f64 {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self, Error> {
+        match value {
+            Value::Number(n) => Ok(n),
+            Value::Int(n) => Ok(n as f64),
+            _ => Err(expected("float", &value)),
+        }
+    }
+}
+
+impl TryFrom<Value> for Decimal {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self, Error> {
+        match value {
+            Value::Decimal(n) => Ok(n),
+            Value::Int(n) => Ok(Decimal::from(n)),
+            _ => Err(expected("decimal", &value)),
+        }
+    }
+}
+
+impl TryFrom<Value> for bool {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self, Error> {
+        match value {
+            Value::Boolean(b) => Ok(b),
+            _ => Err(expected("bool", &value)),
+        }
+    }
+}
+
+impl TryFrom<Value> for String {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self, Error> {
+        match value {
+            Value::String(s) => Ok(s),
+            _ => Err(expected("string", &value)),
+        }
+    }
+}
+
+impl TryFrom<Value> for () {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self, Error> {
+        match value {
+            Value::Unit => Ok(()),
+            _ => Err(expected("unit", &value)),
+        }
+    }
+}
+
+impl<T: TryFrom<Value, Error = Error>> TryFrom<Value> for Vec<T> {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self, Error> {
+        match value {
+            Value::List(items) => items.into_iter().map(T::try_from).collect(),
+            _ => Err(expected("list", &value)),
+        }
+    }
+}
+
+impl<T: TryFrom<Value, Error = Error>> TryFrom<Value> for HashMap<String, T> {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self, Error> {
+        match value {
+            Value::Record(fields) => fields
+                .into_iter()
+                .map(|(key, value)| Ok((key, T::try_from(value)?)))
+                .collect(),
+            _ => Err(expected("record", &value)),
+        }
+    }
+}
+
+impl<T: TryFrom<Value, Error = Error>> TryFrom<Value> for Option<T> {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self, Error> {
+        match value {
+            Value::Option(value) => value.map(|value| T::try_from(*value)).transpose(),
+            _ => Err(expected("option", &value)),
+        }
+    }
+}
+
+// Serde
+
+// Values map onto the serde data model the way they would be written in
+// JSON: records become maps, `ok(v)` and `err(e)` single-entry maps, and
+// variants a map from their tag to their fields. Decimals are written as
+// strings to stay exact, regexes as their pattern. Functions cannot be
+// serialized.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Value {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::{Error as _, SerializeMap};
+
+        match self {
+            Value::Int(n) => serializer.serialize_i64(*n),
+            Value::Number(n) => serializer.serialize_f64(*n),
+            Value::Decimal(n) => serializer.serialize_str(&n.to_string()),
+            Value::String(s) => serializer.serialize_str(s),
+            Value::Boolean(b) => serializer.serialize_bool(*b),
+            Value::Unit => serializer.serialize_unit(),
+            Value::Option(None) => serializer.serialize_none(),
+            Value::Option(Some(value)) => serializer.serialize_some(value.as_ref()),
+            Value::Result(result) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                match result {
+                    Ok(value) => map.serialize_entry("ok", value.as_ref())?,
+                    Err(error) => map.serialize_entry("err", error.as_ref())?,
+                }
+                map.end()
+            }
+            Value::Variant { tag, fields } => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry(tag, fields)?;
+                map.end()
+            }
+            Value::List(items) => serializer.collect_seq(items),
+            Value::Record(fields) => serializer.collect_map(fields),
+            Value::Regex(regex) => serializer.serialize_str(regex.0.as_str()),
+            Value::Func { .. } | Value::BuiltinFunc { .. } | Value::Closure(_) => {
+                Err(S::Error::custom("functions cannot be serialized"))
+            }
+        }
+    }
+}
+
+// Any self-describing input, e.g. JSON, becomes lists, records and
+// scalars. `null` becomes unit.
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Value {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+#[cfg(feature = "serde")]
+struct ValueVisitor;
+
+#[cfg(feature = "serde")]
+impl<'de> serde::de::Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("a Dolang value")
+    }
+
+    fn visit_bool<E>(self, b: bool) -> Result<Value, E> {
+        Ok(Value::Boolean(b))
+    }
+
+    fn visit_i64<E>(self, n: i64) -> Result<Value, E> {
+        Ok(Value::Int(n))
+    }
+
+    fn visit_u64<E>(self, n: u64) -> Result<Value, E> {
+        Ok(i64::try_from(n).map_or(Value::Number(n as f64), Value::Int))
+    }
+
+    fn visit_f64<E>(self, n: f64) -> Result<Value, E> {
+        Ok(Value::Number(n))
+    }
+
+    fn visit_str<E>(self, s: &str) -> Result<Value, E> {
+        Ok(Value::String(s.to_string()))
+    }
+
+    fn visit_string<E>(self, s: String) -> Result<Value, E> {
+        Ok(Value::String(s))
+    }
+
+    fn visit_unit<E>(self) -> Result<Value, E> {
+        Ok(Value::Unit)
+    }
+
+    fn visit_none<E>(self) -> Result<Value, E> {
+        Ok(Value::Option(None))
+    }
+
+    fn visit_some<D: serde::Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        let value = serde::Deserialize::deserialize(deserializer)?;
+        Ok(Value::Option(Some(Box::new(value))))
+    }
+
+    fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut items = im_rc::Vector::new();
+        while let Some(item) = seq.next_element()? {
+            items.push_back(item);
+        }
+        Ok(Value::List(items))
+    }
+
+    fn visit_map<A: serde::de::MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut fields = im_rc::OrdMap::new();
+        while let Some((key, value)) = map.next_entry::<String, Value>()? {
+            fields.insert(key, value);
+        }
+        Ok(Value::Record(fields))
+    }
+}
@@ -1,32 +1,57 @@
+use std::rc::Rc;
+
 use crate::eval::builtin::builtin::BUILTIN_FUNCTIONS;
-use crate::eval::value::{BuiltinFuncArgs, Value};
+use crate::eval::context::Io;
+use crate::eval::value::Value;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Env {
-    variables: std::collections::HashMap<String, Value>,
-    parent: Option<Box<Env>>,
+    variables: im_rc::HashMap<String, Value>,
+    slots: Vec<Value>,
+    parent: Option<Rc<Env>>,
+    io: Io, // shared by every frame, set on the global environment
 }
 
 impl Env {
-    pub fn new(parent: Option<Box<Env>>) -> Self {
-        let mut variables = std::collections::HashMap::new();
-
-        // register built-in functions
-        for func in BUILTIN_FUNCTIONS {
-            variables.insert(
-                func.name.to_string(),
-                Value::BuiltinFunc {
-                    name: func.name.to_string(),
-                    func: func.func,
-                    args: BuiltinFuncArgs {
-                        length: func.args_len,
-                        curried: vec![],
-                    },
-                },
-            );
+    pub fn new(parent: Option<Rc<Env>>) -> Self {
+        let mut variables = im_rc::HashMap::new();
+
+        // register built-in functions in the global environment
+        if parent.is_none() {
+            for func in BUILTIN_FUNCTIONS {
+                variables.insert(func.name.to_string(), func.to_value());
+            }
         }
 
-        Env { variables, parent }
+        let io = match &parent {
+            Some(parent) => parent.io.clone(),
+            None => Io::default(),
+        };
+        Env {
+            variables,
+            slots: vec![],
+            parent,
+            io,
+        }
+    }
+
+    // A global environment whose builtins use the given streams.
+    pub fn with_io(io: Io) -> Self {
+        Env {
+            io,
+            ..Env::new(None)
+        }
+    }
+
+    // Creates the frame of a function call, the arguments fill the slots
+    // the resolver assigned to the parameters.
+    pub fn frame(parent: Rc<Env>, args: Vec<Value>) -> Self {
+        Env {
+            variables: im_rc::HashMap::new(),
+            slots: args,
+            io: parent.io.clone(),
+            parent: Some(parent),
+        }
     }
 
     pub fn set(&mut self, name: String, value: Value) {
@@ -42,4 +67,25 @@ impl Env {
             None
         }
     }
+
+    pub fn io(&self) -> &Io {
+        &self.io
+    }
+
+    pub fn get_slot(&self, depth: usize, slot: usize) -> Option<&Value> {
+        let mut env = self;
+        for _ in 0..depth {
+            env = env.parent.as_deref()?;
+        }
+        env.slots.get(slot)
+    }
+
+    // Names of every variable reachable by name, used to seed the resolver.
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.variables.keys().cloned().collect();
+        if let Some(parent) = &self.parent {
+            names.extend(parent.names());
+        }
+        names
+    }
 }
@@ -0,0 +1,141 @@
+use std::fmt;
+
+use crate::eval::value::Value;
+use crate::token::Range;
+
+// Where a runtime error comes from. Scripts see it as the `kind` field of
+// the errors caught by `try`, which lets permission and limit errors pass.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorKind {
+    Syntax,     // the source couldn't be parsed or refers to undefined names
+    Runtime,    // raised by the language itself: operators, calls, matches
+    Builtin,    // raised by a builtin function
+    User,       // raised by the script with `fail`
+    Assertion,  // raised by a failing `assert`
+    Permission, // denied by the sandbox
+    Limit,      // the run used more than its limits allow
+}
+
+impl ErrorKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ErrorKind::Syntax => "syntax",
+            ErrorKind::Runtime => "runtime",
+            ErrorKind::Builtin => "builtin",
+            ErrorKind::User => "user",
+            ErrorKind::Assertion => "assertion",
+            ErrorKind::Permission => "permission",
+            ErrorKind::Limit => "limit",
+        }
+    }
+}
+
+// A call an error went through on its way out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceFrame {
+    pub function: String,
+    pub span: Option<Range>, // the call site, that of the builtin for calls made by builtins
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error {
+    pub kind: ErrorKind,
+    pub message: String,
+    pub span: Option<Range>,    // the call that raised it, for builtin errors
+    pub trace: Vec<TraceFrame>, // innermost call first
+}
+
+impl Error {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Error {
+            kind,
+            message: message.into(),
+            span: None,
+            trace: vec![],
+        }
+    }
+
+    // Keeps the innermost span, errors passing through enclosing calls
+    // still point at the call that raised them.
+    pub fn with_span(mut self, span: &Range) -> Self {
+        if self.span.is_none() {
+            self.span = Some(span.clone());
+        }
+        self
+    }
+
+    // Whether `try` may catch the error. Sandbox denials and exceeded limits
+    // end the run, a script must not be able to carry on past them.
+    pub fn is_catchable(&self) -> bool {
+        !matches!(self.kind, ErrorKind::Permission | ErrorKind::Limit)
+    }
+
+    // Records that the error left `function`, called at `span`.
+    pub fn in_call(mut self, function: &str, span: Option<&Range>) -> Self {
+        self.trace.push(TraceFrame {
+            function: function.to_string(),
+            span: span.cloned(),
+        });
+        self
+    }
+
+    // One line per call of the trace, innermost first. Runs of the same
+    // call, as left by deep recursion, are shown once with their count.
+    pub fn backtrace(&self) -> String {
+        let mut lines: Vec<String> = vec![];
+        let mut frames = self.trace.iter().peekable();
+        while let Some(frame) = frames.next() {
+            lines.push(match &frame.span {
+                Some(span) => format!(
+                    "  in {} (line {}, column {})",
+                    frame.function, span.start.line, span.start.column
+                ),
+                None => format!("  in {}", frame.function),
+            });
+            let mut repeated = 0;
+            while frames.next_if_eq(&frame).is_some() {
+                repeated += 1;
+            }
+            if repeated > 0 {
+                lines.push(format!("  ... repeated {} more times", repeated));
+            }
+        }
+        lines.join("\n")
+    }
+
+    // The `{ kind, message }` record a caught error evaluates to.
+    pub fn to_value(&self) -> Value {
+        let mut record = im_rc::OrdMap::new();
+        record.insert(
+            "kind".to_string(),
+            Value::String(self.kind.name().to_string()),
+        );
+        record.insert("message".to_string(), Value::String(self.message.clone()));
+        Value::Record(record)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.span {
+            Some(span) => write!(
+                f,
+                "{} (line {}, column {})",
+                self.message, span.start.line, span.start.column
+            ),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl From<String> for Error {
+    fn from(message: String) -> Self {
+        Error::new(ErrorKind::Runtime, message)
+    }
+}
+
+impl From<&str> for Error {
+    fn from(message: &str) -> Self {
+        Error::new(ErrorKind::Runtime, message)
+    }
+}
@@ -1,23 +1,60 @@
-use crate::ast::{CompOp, Expr, FactorOp, LogicOp, Pattern, Stmt, TermOp, UnaryOp, AST};
+use std::rc::Rc;
+
+use crate::ast::{Expr, Segment, Stmt, AST};
+use crate::eval::context::{call_builtin, Io};
 use crate::eval::env::Env;
+use crate::eval::error::Error;
+use crate::eval::ops;
 use crate::eval::value::{BuiltinFuncArgs, Value};
+use crate::token::Range;
+use crate::vm::vm::{self, Closure};
 
-pub fn eval(ast: AST, env: &mut Env) -> Result<(), String> {
+pub fn eval(ast: AST, env: &mut Env) -> Result<(), Error> {
     for stmt in &ast.stmts {
-        eval_stmt(&stmt, env)?;
+        eval_stmt(stmt, env)?;
     }
     Ok(())
 }
 
-pub fn eval_stmt(stmt: &Stmt, env: &mut Env) -> Result<(), String> {
+pub fn eval_stmt(stmt: &Stmt, env: &mut Env) -> Result<(), Error> {
     match stmt {
         Stmt::Expr(expr) => {
             eval_expr(expr, env)?;
             Ok(())
         }
         Stmt::Let { name, val } => {
-            let val = eval_expr(val, env)?;
-            env.set(name.clone(), val);
+            let mut value = eval_expr(val, env)?;
+            // Functions defined by a binding are named after it.
+            if let (
+                Expr::Func { .. },
+                Value::Func {
+                    name: func_name, ..
+                },
+            ) = (val, &mut value)
+            {
+                *func_name = name.clone();
+            }
+            env.set(name.clone(), value);
+            Ok(())
+        }
+        Stmt::Type { variants, .. } => {
+            for variant in variants {
+                let constructor = if variant.fields.is_empty() {
+                    Value::Variant {
+                        tag: variant.name.clone(),
+                        fields: vec![],
+                    }
+                } else {
+                    Value::Func {
+                        name: variant.name.clone(),
+                        params: variant.fields.clone(),
+                        body: Box::new(variant.constructor()),
+                        env: env.clone(),
+                        curried: vec![],
+                    }
+                };
+                env.set(variant.name.clone(), constructor);
+            }
             Ok(())
         }
         _ => {
@@ -27,12 +64,15 @@ pub fn eval_stmt(stmt: &Stmt, env: &mut Env) -> Result<(), String> {
     }
 }
 
-pub fn eval_expr(expr: &Expr, env: &mut Env) -> Result<Value, String> {
+pub fn eval_expr(expr: &Expr, env: &mut Env) -> Result<Value, Error> {
+    env.io().budget.step()?;
     match expr {
         Expr::Func { params, body } => Ok(Value::Func {
+            name: "<lambda>".to_string(),
             params: params.clone(),
             body: body.clone(),
             env: env.clone(),
+            curried: vec![],
         }),
         Expr::If { cond, then, else_ } => {
             let cond_val = eval_expr(cond, env)?;
@@ -45,438 +85,285 @@ pub fn eval_expr(expr: &Expr, env: &mut Env) -> Result<Value, String> {
         Expr::Match { cond, cases } => {
             let cond_val = eval_expr(cond, env)?;
             for case in cases {
-                match &case.pattern {
-                    Pattern::Number(n) => {
-                        if let Value::Number(val) = cond_val {
-                            if val == *n {
-                                return eval_expr(&case.body, env);
-                            }
-                        }
-                    }
-                    Pattern::String(s) => {
-                        if let Value::String(ref val) = cond_val {
-                            if *val == *s {
-                                return eval_expr(&case.body, env);
-                            }
-                        }
-                    }
-                    Pattern::Boolean(b) => {
-                        if let Value::Boolean(val) = cond_val {
-                            if val == *b {
-                                return eval_expr(&case.body, env);
-                            }
-                        }
-                    }
-                    Pattern::Wildcard => {
+                if let Some(bindings) = ops::destructure(&case.pattern, &cond_val) {
+                    if bindings.is_empty() {
                         return eval_expr(&case.body, env);
                     }
+                    // Bound names get a frame of their own, like parameters.
+                    let mut frame = Env::frame(Rc::new(env.clone()), bindings);
+                    return eval_expr(&case.body, &mut frame);
                 }
             }
             Err("No matching case found".into())
         }
+        Expr::Try { body, fallback } => match (eval_expr(body, env), fallback) {
+            (Err(error), _) if !error.is_catchable() => Err(error),
+            (Ok(value), Some(_)) => Ok(value),
+            (Ok(value), None) => Ok(Value::Result(Ok(Box::new(value)))),
+            (Err(_), Some(fallback)) => eval_expr(fallback, env),
+            (Err(error), None) => Ok(Value::Result(Err(Box::new(error.to_value())))),
+        },
         Expr::List(items) => {
-            let mut values = Vec::new();
+            env.io().budget.list(items.len())?;
+            let mut values = im_rc::Vector::new();
             for item in items {
-                values.push(eval_expr(item, env)?);
+                values.push_back(eval_expr(item, env)?);
             }
             Ok(Value::List(values))
         }
         Expr::Record(fields) => {
-            let mut record = std::collections::HashMap::new();
+            env.io().budget.alloc(fields.len())?;
+            let mut record = im_rc::OrdMap::new();
             for (key, value) in fields {
                 let val = eval_expr(value, env)?;
                 record.insert(key.clone(), val);
             }
             Ok(Value::Record(record))
         }
-        Expr::Pipe { left, right } => {
+        Expr::Update { record, fields } => {
+            let record = eval_expr(record, env)?;
+            let mut values = Vec::new();
+            for (key, value) in fields {
+                values.push((key.clone(), eval_expr(value, env)?));
+            }
+            ops::update(record, values, &env.io().budget)
+        }
+        Expr::Pipe { left, right, span } => {
             let left_val = eval_expr(left, env)?;
             let right_val = eval_expr(right, env)?;
-            match right_val {
-                Value::Func { params, body, env } => {
-                    let mut new_env = Env::new(Some(Box::new(env.clone())));
-                    new_env.set(params[0].clone(), left_val);
-                    eval_expr(&body, &mut new_env)
-                }
-                Value::BuiltinFunc {
-                    name: _,
-                    func,
-                    args,
-                } => {
-                    let mut arg_vals = Vec::new();
-                    for arg in args.curried.iter() {
-                        arg_vals.push(arg.clone());
-                    }
-                    arg_vals.push(left_val);
-                    func(arg_vals)
-                }
-                _ => Err("Pipe requires a function on the right".into()),
+            if !right_val.is_function() {
+                return Err("Pipe requires a function on the right".into());
             }
+            apply(right_val, vec![left_val], env.io(), Some(span))
         }
         Expr::Logic { left, op, right } => {
             let left_val = eval_expr(left, env)?;
             let right_val = eval_expr(right, env)?;
-            match op {
-                LogicOp::And => match (left_val, right_val) {
-                    (Value::Boolean(l), Value::Boolean(r)) => Ok(Value::Boolean(l && r)),
-                    _ => Err("Logical AND requires boolean operands".into()),
-                },
-                LogicOp::Or => match (left_val, right_val) {
-                    (Value::Boolean(l), Value::Boolean(r)) => Ok(Value::Boolean(l || r)),
-                    _ => Err("Logical OR requires boolean operands".into()),
-                },
-            }
+            ops::logic(op, left_val, right_val)
         }
         Expr::Comp { left, op, right } => {
             let left_val = eval_expr(left, env)?;
             let right_val = eval_expr(right, env)?;
-            match op {
-                CompOp::Is => match (left_val, right_val) {
-                    (Value::Number(l), Value::Number(r)) => Ok(Value::Boolean(l == r)),
-                    (Value::String(l), Value::String(r)) => Ok(Value::Boolean(l == r)),
-                    (Value::Boolean(l), Value::Boolean(r)) => Ok(Value::Boolean(l == r)),
-                    _ => Err("Equality comparison requires same type".into()),
-                },
-                CompOp::IsNot => match (left_val, right_val) {
-                    (Value::Number(l), Value::Number(r)) => Ok(Value::Boolean(l != r)),
-                    (Value::String(l), Value::String(r)) => Ok(Value::Boolean(l != r)),
-                    (Value::Boolean(l), Value::Boolean(r)) => Ok(Value::Boolean(l != r)),
-                    _ => Err("Inequality comparison requires same type".into()),
-                },
-                CompOp::In => match (left_val, right_val) {
-                    (Value::String(s), Value::List(l)) => {
-                        Ok(Value::Boolean(l.iter().any(|v| v == &Value::String(s.clone()))))
-                    }
-                    (Value::Number(n), Value::List(l)) => {
-                        Ok(Value::Boolean(l.iter().any(|v| v == &Value::Number(n))))
-                    }
-                    (Value::Boolean(b), Value::List(l)) => {
-                        Ok(Value::Boolean(l.iter().any(|v| v == &Value::Boolean(b))))
-                    }
-                    (Value::String(s), Value::Record(r)) => Ok(Value::Boolean(r.contains_key(&s))),
-                    (Value::String(s), Value::String(r)) => Ok(Value::Boolean(r.contains(&s))),
-                    _ => Err("IN operator requires a list on the right".into()),
-                },
-                CompOp::LessThan => match (left_val, right_val) {
-                    (Value::Number(l), Value::Number(r)) => Ok(Value::Boolean(l < r)),
-                    _ => Err("Less than comparison requires number operands".into()),
-                },
-                CompOp::LessThanOrEqual => match (left_val, right_val) {
-                    (Value::Number(l), Value::Number(r)) => Ok(Value::Boolean(l <= r)),
-                    _ => Err("Less than or equal comparison requires number operands".into()),
-                },
-                CompOp::GreaterThan => match (left_val, right_val) {
-                    (Value::Number(l), Value::Number(r)) => Ok(Value::Boolean(l > r)),
-                    _ => Err("Greater than comparison requires number operands".into()),
-                },
-                CompOp::GreaterThanOrEqual => match (left_val, right_val) {
-                    (Value::Number(l), Value::Number(r)) => Ok(Value::Boolean(l >= r)),
-                    _ => Err("Greater than or equal comparison requires number operands".into()),
-                },
-            }
+            ops::comp(op, left_val, right_val)
         }
         Expr::Range { start, end } => {
             let start_val = eval_expr(start, env)?;
             let end_val = eval_expr(end, env)?;
-            match (start_val, end_val) {
-                (Value::Number(s), Value::Number(e)) => {
-                    if s <= e {
-                        let mut range = Vec::new();
-                        for i in s as usize..=e as usize {
-                            range.push(Value::Number(i as f64));
-                        }
-                        Ok(Value::List(range))
-                    } else {
-                        Err("Start of range must be less than or equal to end".into())
-                    }
-                }
-                _ => Err("Range requires number operands".into()),
-            }
+            ops::range(start_val, end_val, &env.io().budget)
         }
         Expr::Term { left, op, right } => {
             let left_val = eval_expr(left, env)?;
             let right_val = eval_expr(right, env)?;
-            match op {
-                TermOp::Plus => match (left_val, right_val) {
-                    (Value::Number(l), Value::Number(r)) => Ok(Value::Number(l + r)),
-                    (Value::String(l), Value::String(r)) => Ok(Value::String(l + &r)),
-                    _ => Err("Addition requires number or string operands".into()),
-                },
-                TermOp::Minus => match (left_val, right_val) {
-                    (Value::Number(l), Value::Number(r)) => Ok(Value::Number(l - r)),
-                    _ => Err("Subtraction requires number operands".into()),
-                },
-            }
+            ops::term(op, left_val, right_val)
         }
         Expr::Factor { left, op, right } => {
             let left_val = eval_expr(left, env)?;
             let right_val = eval_expr(right, env)?;
-            match op {
-                FactorOp::Multiply => match (left_val, right_val) {
-                    (Value::Number(l), Value::Number(r)) => Ok(Value::Number(l * r)),
-                    _ => Err("Multiplication requires number operands".into()),
-                },
-                FactorOp::Divide => match (left_val, right_val) {
-                    (Value::Number(l), Value::Number(r)) => {
-                        if r == 0.0 {
-                            Err("Division by zero".into())
-                        } else {
-                            Ok(Value::Number(l / r))
-                        }
-                    }
-                    _ => Err("Division requires number operands".into()),
-                },
-                FactorOp::Modulus => match (left_val, right_val) {
-                    (Value::Number(l), Value::Number(r)) => Ok(Value::Number(l % r)),
-                    _ => Err("Modulus requires number operands".into()),
-                },
-            }
+            ops::factor(op, left_val, right_val)
         }
         Expr::Unary { op, right } => {
             let right_val = eval_expr(right, env)?;
-            match op {
-                UnaryOp::Minus => match right_val {
-                    Value::Number(n) => Ok(Value::Number(-n)),
-                    _ => Err("Unary minus requires number operand".into()),
-                },
-                UnaryOp::Not => match right_val {
-                    Value::Boolean(b) => Ok(Value::Boolean(!b)),
-                    _ => Err("Logical NOT requires boolean operand".into()),
-                },
-            }
+            ops::unary(op, right_val)
         }
         Expr::Identifier(expr) => {
             if let Some(value) = env.get(&expr.to_string()) {
-                Ok(match value {
-                    Value::Number(n) => Value::Number(*n),
-                    Value::String(s) => Value::String(s.clone()),
-                    Value::Boolean(b) => Value::Boolean(*b),
-                    Value::List(l) => Value::List(l.to_vec()),
-                    Value::Record(r) => Value::Record(r.clone()),
-                    Value::Func { params, body, env } => Value::Func {
-                        params: params.clone(),
-                        body: body.clone(),
-                        env: env.clone(),
-                    },
-                    Value::BuiltinFunc { name, func, args } => Value::BuiltinFunc {
-                        name: name.clone(),
-                        func: func.clone(),
-                        args: args.clone(),
-                    },
-                })
+                Ok(value.clone())
             } else {
-                Err(format!("Undefined variable: {}", expr))
+                Err(format!("Undefined variable: {}", expr).into())
             }
         }
+        Expr::Local { name, depth, slot } => env
+            .get_slot(*depth, *slot)
+            .cloned()
+            .ok_or_else(|| format!("Undefined variable: {}", name).into()),
         Expr::Index { list, index } => {
             let list_val = eval_expr(list, env)?;
             let index_val = eval_expr(index, env)?;
-            match (list_val, index_val) {
-                // Indexing list
-                (Value::List(l), Value::Number(i)) => {
-                    let idx = i as usize;
-                    if idx < l.len() {
-                        Ok(l[idx].clone())
-                    } else {
-                        Err(format!("Index out of bounds: {}", idx))
-                    }
-                }
-                (Value::List(l), Value::List(i)) => {
-                    let mut values = Vec::new();
-                    let idx = i
-                        .iter()
-                        .filter_map(|v| match v {
-                            Value::Number(n) => Some(*n as usize),
-                            _ => None,
-                        })
-                        .collect::<Vec<_>>();
-                    for idx in idx {
-                        if idx < l.len() {
-                            values.push(l[idx].clone());
-                        } else {
-                            return Err(format!("Index out of bounds: {}", idx));
-                        }
-                    }
-                    Ok(Value::List(values))
-                }
-                // Indexing string
-                (Value::String(s), Value::Number(i)) => {
-                    let idx = i as usize;
-                    if idx < s.len() {
-                        Ok(Value::String(s[idx..idx + 1].to_string()))
-                    } else {
-                        Err(format!("Index out of bounds: {}", idx))
-                    }
-                }
-                (Value::String(s), Value::List(i)) => {
-                    let start = i[0].clone();
-                    let end = i[i.len() - 1].clone();
-                    match (start, end) {
-                        (Value::Number(start), Value::Number(end)) => {
-                            let start = start as usize;
-                            let end = end as usize;
-                            if start > end || end > s.len() {
-                                return Err(format!("Index out of bounds: {}..{}", start, end));
-                            }
-                            Ok(Value::String(s[start..end].to_string()))
-                        }
-                        _ => return Err("Indexing requires a number".into()),
-                    }
-                }
-                _ => Err("Indexing requires a list and a number".into()),
-            }
+            ops::index(list_val, index_val)
         }
         Expr::Access { record, field } => {
             let record_val = eval_expr(record, env)?;
-            match record_val {
-                Value::Record(r) => {
-                    if let Some(value) = r.get(field) {
-                        Ok(value.clone())
-                    } else {
-                        Err(format!("Field not found: {}", field))
-                    }
-                }
-                _ => Err("Access requires a record".into()),
+            ops::access(record_val, field)
+        }
+        Expr::Construct { name, args } => {
+            let mut fields = Vec::new();
+            for arg in args {
+                fields.push(eval_expr(arg, env)?);
             }
+            Ok(Value::Variant {
+                tag: name.clone(),
+                fields,
+            })
         }
         Expr::Slice { list, start, end } => {
             let list_val = eval_expr(list, env)?;
-            match list_val {
-                Value::List(l) => {
-                    let start_val = start.as_ref().map(|s| eval_expr(s, env)).transpose()?;
-                    let end_val = end.as_ref().map(|e| eval_expr(e, env)).transpose()?;
-                    let start_idx = start_val
-                        .and_then(|v| match v {
-                            Value::Number(n) => Some(n as usize),
-                            _ => None,
-                        })
-                        .unwrap_or(0);
-                    let end_idx = end_val
-                        .and_then(|v| match v {
-                            Value::Number(n) => Some(n as usize),
-                            _ => None,
-                        })
-                        .unwrap_or(l.len());
-                    if start_idx <= end_idx && end_idx <= l.len() {
-                        Ok(Value::List(l[start_idx..end_idx].to_vec()))
-                    } else {
-                        Err(format!(
-                            "Slice indices out of bounds: {}..{}",
-                            start_idx, end_idx
-                        ))
-                    }
-                }
-                Value::String(s) => {
-                    let start_val = start.as_ref().map(|s| eval_expr(s, env)).transpose()?;
-                    let end_val = end.as_ref().map(|e| eval_expr(e, env)).transpose()?;
-                    let start_idx = start_val
-                        .and_then(|v| match v {
-                            Value::Number(n) => Some(n as usize),
-                            _ => None,
-                        })
-                        .unwrap_or(0);
-                    let end_idx = end_val
-                        .and_then(|v| match v {
-                            Value::Number(n) => Some(n as usize),
-                            _ => None,
-                        })
-                        .unwrap_or(s.len());
-                    if start_idx <= end_idx && end_idx <= s.len() {
-                        Ok(Value::String(s[start_idx..end_idx].to_string()))
-                    } else {
-                        Err(format!(
-                            "Slice indices out of bounds: {}..{}",
-                            start_idx, end_idx
-                        ))
-                    }
-                }
-                _ => Err("Slicing requires a list".into()),
-            }
+            let start_val = start.as_ref().map(|s| eval_expr(s, env)).transpose()?;
+            let end_val = end.as_ref().map(|e| eval_expr(e, env)).transpose()?;
+            ops::slice(list_val, start_val, end_val)
         }
         Expr::Call {
             name: call_name,
             args: call_args,
+            span,
         } => {
             let func_val = eval_expr(call_name, env)?;
-            match func_val {
-                Value::Func {
-                    params,
-                    body,
-                    env: func_env,
-                } => {
-                    // normal function call
-                    if call_args.len() == params.len() {
-                        let mut new_env = Env::new(Some(Box::new(func_env)));
-                        for (arg, arg_name) in call_args.iter().zip(params) {
-                            let arg_val = eval_expr(arg, env)?;
-                            new_env.set(arg_name, arg_val);
-                        }
-                        eval_expr(&body, &mut new_env)
-                    }
-                    // currying
-                    else if params.len() > call_args.len() {
-                        let mut new_env = Env::new(Some(Box::new(func_env)));
-                        for (arg, arg_name) in call_args.iter().zip(params.iter()) {
-                            let arg_val = eval_expr(arg, env)?;
-                            new_env.set(arg_name.clone(), arg_val);
-                        }
-                        let remaining_params = params[call_args.len()..].to_vec();
-                        let remaining_body = body.clone();
-                        Ok(Value::Func {
-                            params: remaining_params,
-                            body: remaining_body,
-                            env: new_env,
-                        })
-                    } else {
-                        Err(format!(
-                            "Function {:?} requires {} arguments, but got {}",
-                            call_name,
-                            params.len(),
-                            call_args.len()
-                        ))
-                    }
-                }
-                Value::BuiltinFunc { name, func, args } => {
-                    // normal function call
-                    if call_args.len() == args.length {
-                        let mut arg_vals = Vec::new();
-                        for arg in args.curried.iter() {
-                            arg_vals.push(arg.clone());
-                        }
-                        for arg in call_args {
-                            arg_vals.push(eval_expr(arg, env)?);
-                        }
-                        func(arg_vals)
-                    }
-                    // currying
-                    else if args.length > call_args.len() {
-                        let mut new_args = args.curried.clone();
-                        for arg in call_args {
-                            let arg_val = eval_expr(arg, env)?;
-                            new_args.push(arg_val);
-                        }
-                        Ok(Value::BuiltinFunc {
-                            name,
-                            func: func.clone(),
-                            args: BuiltinFuncArgs {
-                                length: args.length - call_args.len(),
-                                curried: new_args,
-                            },
-                        })
-                    } else {
-                        Err(format!(
-                            "Function {:?} requires {} arguments, but got {}",
-                            call_name,
-                            args.length,
-                            call_args.len()
-                        ))
-                    }
-                }
-                _ => Err("Function call requires a function".into()),
+            let mut arg_vals = Vec::new();
+            for arg in call_args {
+                arg_vals.push(eval_expr(arg, env)?);
             }
+            apply(func_val, arg_vals, env.io(), Some(span))
         }
+        Expr::Int(n) => Ok(Value::Int(*n)),
         Expr::Number(n) => Ok(Value::Number(*n)),
+        Expr::Decimal(d) => Ok(Value::Decimal(*d)),
         Expr::String(s) => Ok(Value::String(s.clone())),
+        Expr::Interpolated(segments) => {
+            let mut values = Vec::new();
+            for segment in segments {
+                values.push(match segment {
+                    Segment::Text(text) => Value::String(text.clone()),
+                    Segment::Expr(expr) => eval_expr(expr, env)?,
+                });
+            }
+            Ok(ops::interpolate(&values))
+        }
         Expr::Boolean(b) => Ok(Value::Boolean(*b)),
+        Expr::Unit => Ok(Value::Unit),
+        Expr::None => Ok(Value::Option(None)),
+    }
+}
+
+// Applies a function value to arguments, for calls, pipes and builtins
+// alike. Missing arguments give a partially applied function and extra
+// ones are passed on to the function's result. `span` is the call, when it
+// was written as one.
+pub fn apply(
+    func: Value,
+    mut args: Vec<Value>,
+    io: &Io,
+    span: Option<&Range>,
+) -> Result<Value, Error> {
+    let missing = match &func {
+        Value::Func {
+            params, curried, ..
+        } => params.len() - curried.len(),
+        Value::BuiltinFunc { args, .. } => args.length,
+        Value::Closure(closure) => closure.proto.params.len() - closure.bound.len(),
+        _ => return Err("Function call requires a function".into()),
+    };
+    if args.len() < missing {
+        return Ok(curry(func, args));
+    }
+    let extra = args.split_off(missing);
+    let name = func_name(&func);
+    let result = call(func, args, io, span)?;
+    if extra.is_empty() {
+        Ok(result)
+    } else if result.is_function() {
+        apply(result, extra, io, span)
+    } else {
+        Err(format!(
+            "Function {} requires {} arguments, but got {}",
+            name,
+            missing,
+            missing + extra.len()
+        )
+        .into())
+    }
+}
+
+fn func_name(func: &Value) -> String {
+    match func {
+        Value::Func { name, .. } | Value::BuiltinFunc { name, .. } => name.clone(),
+        Value::Closure(closure) => closure.proto.name.clone(),
+        _ => String::new(),
+    }
+}
+
+// Binds some of the arguments a function is missing.
+fn curry(func: Value, args: Vec<Value>) -> Value {
+    match func {
+        Value::Func {
+            name,
+            params,
+            body,
+            env,
+            mut curried,
+        } => {
+            curried.extend(args);
+            Value::Func {
+                name,
+                params,
+                body,
+                env,
+                curried,
+            }
+        }
+        Value::BuiltinFunc {
+            name,
+            func,
+            args: builtin_args,
+        } => {
+            let length = builtin_args.length - args.len();
+            let mut curried = builtin_args.curried;
+            curried.extend(args);
+            Value::BuiltinFunc {
+                name,
+                func,
+                args: BuiltinFuncArgs { length, curried },
+            }
+        }
+        Value::Closure(closure) => {
+            let mut bound = closure.bound.clone();
+            bound.extend(args);
+            Value::Closure(Rc::new(Closure {
+                proto: Rc::clone(&closure.proto),
+                captures: closure.captures.clone(),
+                bound,
+                globals: closure.globals.clone(),
+            }))
+        }
+        other => other,
     }
 }
+
+// Calls a function with exactly the arguments it is missing.
+fn call(func: Value, args: Vec<Value>, io: &Io, span: Option<&Range>) -> Result<Value, Error> {
+    match func {
+        Value::Func {
+            name,
+            body,
+            env,
+            mut curried,
+            ..
+        } => {
+            curried.extend(args);
+            call_func(&body, env, curried).map_err(|e| e.in_call(&name, span))
+        }
+        Value::BuiltinFunc {
+            name,
+            func,
+            args: builtin_args,
+        } => {
+            let mut all_args = builtin_args.curried;
+            all_args.extend(args);
+            call_builtin(&name, &func, all_args, io, span)
+        }
+        Value::Closure(closure) => {
+            vm::call(&closure, args, io).map_err(|e| e.in_call(&closure.proto.name, span))
+        }
+        _ => Err("Function call requires a function".into()),
+    }
+}
+
+// Evaluates the body of a fully applied function in a new frame holding
+// the arguments.
+pub fn call_func(body: &Expr, env: Env, args: Vec<Value>) -> Result<Value, Error> {
+    let budget = Rc::clone(&env.io().budget);
+    let depth = budget.depth();
+    budget.enter(depth + 1)?;
+    let mut frame = Env::frame(Rc::new(env), args);
+    let result = eval_expr(body, &mut frame);
+    budget.leave(depth);
+    result
+}
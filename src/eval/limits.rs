@@ -0,0 +1,167 @@
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+use crate::eval::error::{Error, ErrorKind};
+
+// Caps on the resources a run may use, for scripts that could otherwise
+// hang the interpreter or exhaust its memory. Only calls are capped by
+// default, at `DEFAULT_DEPTH`, and strings built by builtins never exceed
+// `MAX_STRING_LEN`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Limits {
+    pub steps: Option<u64>, // expressions evaluated, or instructions run by the VM
+    pub depth: Option<usize>, // function calls in progress at once, `DEFAULT_DEPTH` if unset
+    pub list_len: Option<usize>, // elements of a single list
+    pub values: Option<u64>, // list elements and record fields created in total, shared ones once
+    pub timeout: Option<Duration>,
+}
+
+// The limits of a run and what it used so far, shared by both engines and
+// the builtins.
+#[derive(Debug)]
+pub struct Budget {
+    limits: Limits,
+    steps: Cell<u64>,
+    depth: Cell<usize>,
+    values: Cell<u64>,
+    deadline: Cell<Option<Instant>>,
+}
+
+// The stack scripts run with. The tree-walking evaluator recurses on the
+// Rust stack for every Dolang call, embedders running scripts on smaller
+// stacks should lower `Limits::depth` to match.
+pub const SCRIPT_STACK_SIZE: usize = 1024 * 1024 * 1024;
+
+// The most stack a Dolang call was measured to take, in debug builds where
+// frames are largest.
+const STACK_PER_CALL: usize = 256 * 1024;
+
+// How deep calls may nest without a `depth` limit, for deep recursion to
+// fail rather than overflow a stack of `SCRIPT_STACK_SIZE`.
+pub const DEFAULT_DEPTH: usize = SCRIPT_STACK_SIZE / STACK_PER_CALL;
+
+// Strings built by builtins are capped even without limits, allocating
+// more than this would abort the process rather than fail.
+const MAX_STRING_LEN: usize = 1 << 30;
+
+// The clock is only read every so many steps, it is slow compared to a
+// step.
+const STEPS_PER_CLOCK_CHECK: u64 = 1024;
+
+impl Budget {
+    pub fn new(limits: Limits) -> Self {
+        let budget = Budget {
+            limits,
+            steps: Cell::new(0),
+            depth: Cell::new(0),
+            values: Cell::new(0),
+            deadline: Cell::new(None),
+        };
+        budget.reset();
+        budget
+    }
+
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    // Starts a new run with nothing used, its timeout counting from now.
+    pub fn reset(&self) {
+        self.steps.set(0);
+        self.depth.set(0);
+        self.values.set(0);
+        self.deadline
+            .set(self.limits.timeout.map(|timeout| Instant::now() + timeout));
+    }
+
+    pub fn step(&self) -> Result<(), Error> {
+        let steps = self.steps.get() + 1;
+        self.steps.set(steps);
+        if let Some(max) = self.limits.steps.filter(|max| steps > *max) {
+            return Err(exceeded(format!("more than {} steps", max)));
+        }
+        if steps.is_multiple_of(STEPS_PER_CLOCK_CHECK) {
+            self.check_deadline()?;
+        }
+        Ok(())
+    }
+
+    fn check_deadline(&self) -> Result<(), Error> {
+        match (self.deadline.get(), self.limits.timeout) {
+            (Some(deadline), Some(timeout)) if Instant::now() >= deadline => {
+                Err(exceeded(format!("timed out after {:?}", timeout)))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth.get()
+    }
+
+    // Enters a call nested `depth` calls deep.
+    pub fn enter(&self, depth: usize) -> Result<(), Error> {
+        let max = self.limits.depth.unwrap_or(DEFAULT_DEPTH);
+        if depth > max {
+            return Err(exceeded(format!("calls nested more than {} deep", max)));
+        }
+        self.depth.set(depth);
+        Ok(())
+    }
+
+    // Goes back to `depth` once calls returned or were unwound.
+    pub fn leave(&self, depth: usize) {
+        self.depth.set(depth);
+    }
+
+    // Accounts for a new list of `len` elements, before building it.
+    pub fn list(&self, len: usize) -> Result<(), Error> {
+        self.list_len(len)?;
+        self.alloc(len)
+    }
+
+    // Checks a list of `len` elements may be built, for lists sharing
+    // their elements with existing ones.
+    pub fn list_len(&self, len: usize) -> Result<(), Error> {
+        if let Some(max) = self.limits.list_len.filter(|max| len > *max) {
+            return Err(exceeded(format!(
+                "list of {} elements, more than {}",
+                len, max
+            )));
+        }
+        Ok(())
+    }
+
+    // Checks a string of `len` bytes can be built, before building it.
+    pub fn string(&self, len: usize) -> Result<(), Error> {
+        if len > MAX_STRING_LEN {
+            return Err(exceeded(format!(
+                "string of {} bytes, more than {}",
+                len, MAX_STRING_LEN
+            )));
+        }
+        Ok(())
+    }
+
+    // Accounts for `count` new values stored in a list or record.
+    pub fn alloc(&self, count: usize) -> Result<(), Error> {
+        let values = self.values.get().saturating_add(count as u64);
+        self.values.set(values);
+        match self.limits.values {
+            Some(max) if values > max => {
+                Err(exceeded(format!("more than {} values allocated", max)))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Default for Budget {
+    fn default() -> Self {
+        Budget::new(Limits::default())
+    }
+}
+
+fn exceeded(message: String) -> Error {
+    Error::new(ErrorKind::Limit, format!("Limit exceeded: {}", message))
+}
@@ -1,4 +1,10 @@
 pub mod builtin;
+pub mod context;
+pub mod convert;
 pub mod env;
+pub mod error;
 pub mod eval;
+pub mod limits;
+pub mod ops;
+pub mod sandbox;
 pub mod value;
@@ -0,0 +1,530 @@
+use std::cmp::Ordering;
+
+use rust_decimal::Decimal;
+
+use crate::ast::{CompOp, FactorOp, LogicOp, Pattern, TermOp, UnaryOp};
+use crate::eval::builtin::io::format_value;
+use crate::eval::error::Error;
+use crate::eval::limits::Budget;
+use crate::eval::value::Value;
+
+// Operator semantics shared by the tree-walking evaluator and the VM.
+
+pub fn logic(op: &LogicOp, left: Value, right: Value) -> Result<Value, Error> {
+    match op {
+        LogicOp::And => match (left, right) {
+            (Value::Boolean(l), Value::Boolean(r)) => Ok(Value::Boolean(l && r)),
+            _ => Err("Logical AND requires boolean operands".into()),
+        },
+        LogicOp::Or => match (left, right) {
+            (Value::Boolean(l), Value::Boolean(r)) => Ok(Value::Boolean(l || r)),
+            _ => Err("Logical OR requires boolean operands".into()),
+        },
+    }
+}
+
+// Mixed integer and float operands are promoted to floats.
+fn floats(left: &Value, right: &Value) -> Option<(f64, f64)> {
+    match (left, right) {
+        (Value::Number(l), Value::Number(r)) => Some((*l, *r)),
+        (Value::Int(l), Value::Number(r)) => Some((*l as f64, *r)),
+        (Value::Number(l), Value::Int(r)) => Some((*l, *r as f64)),
+        (Value::Int(l), Value::Int(r)) => Some((*l as f64, *r as f64)),
+        _ => None,
+    }
+}
+
+// Integers are promoted to decimals exactly. Mixing decimals with floats is
+// refused, it would bring back the rounding errors decimals exist to avoid.
+fn decimals(left: &Value, right: &Value) -> Option<Result<(Decimal, Decimal), Error>> {
+    match (left, right) {
+        (Value::Decimal(l), Value::Decimal(r)) => Some(Ok((*l, *r))),
+        (Value::Decimal(l), Value::Int(r)) => Some(Ok((*l, Decimal::from(*r)))),
+        (Value::Int(l), Value::Decimal(r)) => Some(Ok((Decimal::from(*l), *r))),
+        (Value::Decimal(_), Value::Number(_)) | (Value::Number(_), Value::Decimal(_)) => Some(Err(
+            "Cannot mix decimal and float operands, convert one with decimal() or float()".into(),
+        )),
+        _ => None,
+    }
+}
+
+// Applies the variant of an arithmetic operator that matches the operand
+// types, `None` if they aren't numbers.
+fn arithmetic(
+    left: &Value,
+    right: &Value,
+    int: fn(i64, i64) -> Option<i64>,
+    decimal: fn(Decimal, Decimal) -> Option<Decimal>,
+    float: fn(f64, f64) -> f64,
+) -> Option<Result<Value, Error>> {
+    if let (Value::Int(l), Value::Int(r)) = (left, right) {
+        return Some(int(*l, *r).map(Value::Int).ok_or_else(overflow));
+    }
+    if let Some(operands) = decimals(left, right) {
+        return Some(operands.and_then(|(l, r)| {
+            decimal(l, r)
+                .map(Value::Decimal)
+                .ok_or_else(|| "Decimal overflow".into())
+        }));
+    }
+    floats(left, right).map(|(l, r)| Ok(Value::Number(float(l, r))))
+}
+
+fn is_zero(value: &Value) -> bool {
+    match value {
+        Value::Int(n) => *n == 0,
+        Value::Number(n) => *n == 0.0,
+        Value::Decimal(d) => d.is_zero(),
+        _ => false,
+    }
+}
+
+fn overflow() -> Error {
+    "Integer overflow".into()
+}
+
+// Equality as with `is`: lists, records and variants are equal when their
+// contents are, numbers when their values are. Other values of different
+// types are unequal. Functions can't be compared.
+pub fn equals(left: &Value, right: &Value) -> Result<bool, Error> {
+    match (left, right) {
+        (l, r) if l.is_function() || r.is_function() => Err(functions()),
+        (Value::Int(l), Value::Int(r)) => Ok(l == r),
+        (Value::String(l), Value::String(r)) => Ok(l == r),
+        (Value::Boolean(l), Value::Boolean(r)) => Ok(l == r),
+        (Value::Unit, Value::Unit) => Ok(true),
+        (Value::Regex(l), Value::Regex(r)) => Ok(l == r),
+        (Value::Option(l), Value::Option(r)) => match (l, r) {
+            (Some(l), Some(r)) => equals(l, r),
+            (None, None) => Ok(true),
+            _ => Ok(false),
+        },
+        (Value::Result(l), Value::Result(r)) => match (l, r) {
+            (Ok(l), Ok(r)) | (Err(l), Err(r)) => equals(l, r),
+            _ => Ok(false),
+        },
+        (
+            Value::Variant {
+                tag: l_tag,
+                fields: l_fields,
+            },
+            Value::Variant {
+                tag: r_tag,
+                fields: r_fields,
+            },
+        ) => Ok(l_tag == r_tag
+            && l_fields.len() == r_fields.len()
+            && all_equal(l_fields.iter().zip(r_fields))?),
+        (Value::List(l), Value::List(r)) => Ok(l.len() == r.len() && all_equal(l.iter().zip(r))?),
+        (Value::Record(l), Value::Record(r)) => Ok(l.len() == r.len()
+            && l.keys().eq(r.keys())
+            && all_equal(l.values().zip(r.values()))?),
+        (l, r) => match decimals(l, r) {
+            Some(operands) => operands.map(|(l, r)| l == r),
+            None => Ok(floats(l, r).is_some_and(|(l, r)| l == r)),
+        },
+    }
+}
+
+fn all_equal<'a>(pairs: impl Iterator<Item = (&'a Value, &'a Value)>) -> Result<bool, Error> {
+    for (l, r) in pairs {
+        if !equals(l, r)? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn functions() -> Error {
+    "Functions cannot be compared".into()
+}
+
+// `is` and `is not` refuse values of different types, comparing a string
+// to a number is more likely a mistake than a question.
+fn same_type_equals(left: &Value, right: &Value, comparison: &str) -> Result<bool, Error> {
+    let numbers = floats(left, right).is_some() || decimals(left, right).is_some();
+    let functions = left.is_function() || right.is_function();
+    if left.type_name() != right.type_name() && !numbers && !functions {
+        return Err(format!("{} comparison requires same type", comparison).into());
+    }
+    equals(left, right)
+}
+
+// The order of `<`, `sort`, `min` and `max`: numbers by value, strings
+// alphabetically and lists item by item, a prefix first. None for values
+// that can't be ordered.
+pub fn order(left: &Value, right: &Value) -> Option<Ordering> {
+    match (left, right) {
+        (Value::String(l), Value::String(r)) => Some(l.cmp(r)),
+        (Value::List(l), Value::List(r)) => {
+            for (l, r) in l.iter().zip(r) {
+                match order(l, r)? {
+                    Ordering::Equal => {}
+                    ordering => return Some(ordering),
+                }
+            }
+            Some(l.len().cmp(&r.len()))
+        }
+        _ => compare(left, right),
+    }
+}
+
+fn compare(left: &Value, right: &Value) -> Option<Ordering> {
+    match (left, right) {
+        (Value::Int(l), Value::Int(r)) => Some(l.cmp(r)),
+        (l, r) => match decimals(l, r) {
+            Some(operands) => operands.ok().map(|(l, r)| l.cmp(&r)),
+            None => floats(l, r).and_then(|(l, r)| l.partial_cmp(&r)),
+        },
+    }
+}
+
+fn ordering(left: &Value, right: &Value, comparison: &str) -> Result<Ordering, Error> {
+    match order(left, right) {
+        Some(ordering) => Ok(ordering),
+        None if left.is_function() || right.is_function() => Err(functions()),
+        None => match decimals(left, right) {
+            Some(Err(error)) => Err(error),
+            _ => Err(format!(
+                "{} comparison requires two numbers, strings or lists, but got {} and {}",
+                comparison,
+                left.type_name(),
+                right.type_name()
+            )
+            .into()),
+        },
+    }
+}
+
+pub fn comp(op: &CompOp, left: Value, right: Value) -> Result<Value, Error> {
+    match op {
+        CompOp::Is => same_type_equals(&left, &right, "Equality").map(Value::Boolean),
+        CompOp::IsNot => {
+            same_type_equals(&left, &right, "Inequality").map(|equal| Value::Boolean(!equal))
+        }
+        CompOp::In => match (left, right) {
+            (Value::String(s), Value::Record(r)) => Ok(Value::Boolean(r.contains_key(&s))),
+            (Value::String(s), Value::String(r)) => Ok(Value::Boolean(r.contains(&s))),
+            (needle, Value::List(l)) => {
+                for item in l.iter() {
+                    if equals(&needle, item)? {
+                        return Ok(Value::Boolean(true));
+                    }
+                }
+                Ok(Value::Boolean(false))
+            }
+            _ => Err("IN operator requires a list on the right".into()),
+        },
+        CompOp::LessThan => {
+            ordering(&left, &right, "Less than").map(|ord| Value::Boolean(ord.is_lt()))
+        }
+        CompOp::LessThanOrEqual => {
+            ordering(&left, &right, "Less than or equal").map(|ord| Value::Boolean(ord.is_le()))
+        }
+        CompOp::GreaterThan => {
+            ordering(&left, &right, "Greater than").map(|ord| Value::Boolean(ord.is_gt()))
+        }
+        CompOp::GreaterThanOrEqual => {
+            ordering(&left, &right, "Greater than or equal").map(|ord| Value::Boolean(ord.is_ge()))
+        }
+    }
+}
+
+// Joins the pieces of an interpolated string, formatted like `print`
+// formats values.
+pub fn interpolate(values: &[Value]) -> Value {
+    Value::String(values.iter().map(format_value).collect())
+}
+
+// The list is accounted for in `budget` before it is built, so huge
+// ranges fail instead of exhausting memory.
+pub fn range(start: Value, end: Value, budget: &Budget) -> Result<Value, Error> {
+    match (start, end) {
+        (Value::Int(s), Value::Int(e)) => {
+            if s <= e {
+                let len = usize::try_from(e.abs_diff(s))
+                    .ok()
+                    .and_then(|len| len.checked_add(1))
+                    .unwrap_or(usize::MAX);
+                budget.list(len)?;
+                Ok(Value::List((s..=e).map(Value::Int).collect()))
+            } else {
+                Err("Start of range must be less than or equal to end".into())
+            }
+        }
+        _ => Err("Range requires integer operands".into()),
+    }
+}
+
+pub fn term(op: &TermOp, left: Value, right: Value) -> Result<Value, Error> {
+    match op {
+        TermOp::Plus => match (left, right) {
+            (Value::String(l), Value::String(r)) => Ok(Value::String(l + &r)),
+            (l, r) => arithmetic(&l, &r, i64::checked_add, Decimal::checked_add, |l, r| l + r)
+                .unwrap_or_else(|| Err("Addition requires number or string operands".into())),
+        },
+        TermOp::Minus => arithmetic(
+            &left,
+            &right,
+            i64::checked_sub,
+            Decimal::checked_sub,
+            |l, r| l - r,
+        )
+        .unwrap_or_else(|| Err("Subtraction requires number operands".into())),
+    }
+}
+
+pub fn factor(op: &FactorOp, left: Value, right: Value) -> Result<Value, Error> {
+    match op {
+        FactorOp::Multiply => arithmetic(
+            &left,
+            &right,
+            i64::checked_mul,
+            Decimal::checked_mul,
+            |l, r| l * r,
+        )
+        .unwrap_or_else(|| Err("Multiplication requires number operands".into())),
+        FactorOp::Divide => match (left, right) {
+            (_, r) if is_zero(&r) => Err("Division by zero".into()),
+            // Dividing integers produces a float, `//` is the integer division.
+            (Value::Int(l), Value::Int(r)) => Ok(Value::Number(l as f64 / r as f64)),
+            (l, r) => arithmetic(&l, &r, i64::checked_div, Decimal::checked_div, |l, r| l / r)
+                .unwrap_or_else(|| Err("Division requires number operands".into())),
+        },
+        FactorOp::IntDivide => match (left, right) {
+            (_, r) if is_zero(&r) => Err("Division by zero".into()),
+            // Rounds toward negative infinity, so `(a // b) * b + a % b` is `a`.
+            (Value::Int(l), Value::Int(r)) => {
+                let quotient = l.checked_div(r).ok_or_else(overflow)?;
+                if (l % r != 0) && ((l < 0) != (r < 0)) {
+                    Ok(Value::Int(quotient - 1))
+                } else {
+                    Ok(Value::Int(quotient))
+                }
+            }
+            (l, r) => match decimals(&l, &r) {
+                Some(operands) => {
+                    let (l, r) = operands?;
+                    l.checked_div(r)
+                        .map(|quotient| Value::Decimal(quotient.floor()))
+                        .ok_or_else(|| "Decimal overflow".into())
+                }
+                None => Err("Integer division requires integer or decimal operands".into()),
+            },
+        },
+        FactorOp::Modulus => match (left, right) {
+            (_, r) if is_zero(&r) => Err("Division by zero".into()),
+            // The remainder of `//`, rounded toward negative infinity: it
+            // takes the sign of the divisor, for every type of number.
+            (l, r) => arithmetic(
+                &l,
+                &r,
+                |l, r| {
+                    l.checked_rem(r).map(|m| {
+                        if m != 0 && (m < 0) != (r < 0) {
+                            m + r
+                        } else {
+                            m
+                        }
+                    })
+                },
+                |l, r| {
+                    l.checked_rem(r).map(|m| {
+                        if !m.is_zero() && m.is_sign_negative() != r.is_sign_negative() {
+                            m + r
+                        } else {
+                            m
+                        }
+                    })
+                },
+                |l, r| {
+                    let m = l % r;
+                    if m != 0.0 && (m < 0.0) != (r < 0.0) {
+                        m + r
+                    } else {
+                        m
+                    }
+                },
+            )
+            .unwrap_or_else(|| Err("Modulus requires number operands".into())),
+        },
+    }
+}
+
+pub fn unary(op: &UnaryOp, right: Value) -> Result<Value, Error> {
+    match op {
+        UnaryOp::Minus => match right {
+            Value::Int(n) => n.checked_neg().map(Value::Int).ok_or_else(overflow),
+            Value::Number(n) => Ok(Value::Number(-n)),
+            Value::Decimal(d) => Ok(Value::Decimal(-d)),
+            _ => Err("Unary minus requires number operand".into()),
+        },
+        UnaryOp::Not => match right {
+            Value::Boolean(b) => Ok(Value::Boolean(!b)),
+            _ => Err("Logical NOT requires boolean operand".into()),
+        },
+    }
+}
+
+// Converts an index value, negative indexes are always out of bounds.
+fn to_index(value: &Value) -> Result<usize, Error> {
+    match value {
+        Value::Int(i) => {
+            usize::try_from(*i).map_err(|_| format!("Index out of bounds: {}", i).into())
+        }
+        _ => Err("Index must be an integer".into()),
+    }
+}
+
+pub fn index(list: Value, index: Value) -> Result<Value, Error> {
+    match (list, index) {
+        // Indexing list
+        (Value::List(l), Value::List(i)) => {
+            let mut values = im_rc::Vector::new();
+            for idx in i.iter() {
+                let idx = to_index(idx)?;
+                if idx < l.len() {
+                    values.push_back(l[idx].clone());
+                } else {
+                    return Err(format!("Index out of bounds: {}", idx).into());
+                }
+            }
+            Ok(Value::List(values))
+        }
+        (Value::List(l), i) => {
+            let idx = to_index(&i)?;
+            if idx < l.len() {
+                Ok(l[idx].clone())
+            } else {
+                Err(format!("Index out of bounds: {}", idx).into())
+            }
+        }
+        // Indexing string, by characters rather than bytes
+        (Value::String(s), Value::List(i)) => {
+            let chars: Vec<char> = s.chars().collect();
+            let mut result = String::new();
+            for idx in i.iter() {
+                let idx = to_index(idx)?;
+                match chars.get(idx) {
+                    Some(c) => result.push(*c),
+                    None => return Err(format!("Index out of bounds: {}", idx).into()),
+                }
+            }
+            Ok(Value::String(result))
+        }
+        (Value::String(s), i) => {
+            let idx = to_index(&i)?;
+            match s.chars().nth(idx) {
+                Some(c) => Ok(Value::String(c.to_string())),
+                None => Err(format!("Index out of bounds: {}", idx).into()),
+            }
+        }
+        _ => Err("Indexing requires a list and an integer".into()),
+    }
+}
+
+pub fn slice(list: Value, start: Option<Value>, end: Option<Value>) -> Result<Value, Error> {
+    let start_idx = start.as_ref().map(to_index).transpose()?.unwrap_or(0);
+    let end_idx = end.as_ref().map(to_index).transpose()?;
+    match list {
+        Value::List(l) => {
+            let end_idx = end_idx.unwrap_or(l.len());
+            if start_idx <= end_idx && end_idx <= l.len() {
+                Ok(Value::List(l.skip(start_idx).take(end_idx - start_idx)))
+            } else {
+                Err(format!("Slice indices out of bounds: {}..{}", start_idx, end_idx).into())
+            }
+        }
+        Value::String(s) => {
+            let len = s.chars().count();
+            let end_idx = end_idx.unwrap_or(len);
+            if start_idx <= end_idx && end_idx <= len {
+                let chars = s.chars().skip(start_idx).take(end_idx - start_idx);
+                Ok(Value::String(chars.collect()))
+            } else {
+                Err(format!("Slice indices out of bounds: {}..{}", start_idx, end_idx).into())
+            }
+        }
+        _ => Err("Slicing requires a list".into()),
+    }
+}
+
+pub fn access(record: Value, field: &str) -> Result<Value, Error> {
+    match record {
+        Value::Record(r) => {
+            if let Some(value) = r.get(field) {
+                Ok(value.clone())
+            } else {
+                Err(format!("Field not found: {}", field).into())
+            }
+        }
+        _ => Err("Access requires a record".into()),
+    }
+}
+
+// A copy of `record` with `fields` replaced. Only existing fields can be
+// updated, so that a misspelled name is an error rather than a new field.
+pub fn update(
+    record: Value,
+    fields: Vec<(String, Value)>,
+    budget: &Budget,
+) -> Result<Value, Error> {
+    match record {
+        Value::Record(mut r) => {
+            budget.alloc(fields.len())?;
+            for (key, value) in fields {
+                match r.get_mut(&key) {
+                    Some(field) => *field = value,
+                    None => return Err(format!("Field not found: {}", key).into()),
+                }
+            }
+            Ok(Value::Record(r))
+        }
+        _ => Err("Update requires a record".into()),
+    }
+}
+
+// Matches a value against a pattern. On success, returns the values bound
+// by the pattern in the order of `Pattern::bindings`.
+pub fn destructure(pattern: &Pattern, value: &Value) -> Option<Vec<Value>> {
+    let mut bindings = Vec::new();
+    if bind(pattern, value, &mut bindings) {
+        Some(bindings)
+    } else {
+        None
+    }
+}
+
+fn bind(pattern: &Pattern, value: &Value, bindings: &mut Vec<Value>) -> bool {
+    let literal = match (pattern, value) {
+        (Pattern::Wildcard, _) => return true,
+        (Pattern::Binding(_), _) => {
+            bindings.push(value.clone());
+            return true;
+        }
+        (Pattern::Some(inner), Value::Option(Some(value)))
+        | (Pattern::Ok(inner), Value::Result(Ok(value)))
+        | (Pattern::Err(inner), Value::Result(Err(value))) => {
+            return bind(inner, value, bindings);
+        }
+        (Pattern::Constructor { name, args }, Value::Variant { tag, fields }) => {
+            return name == tag
+                && args.len() == fields.len()
+                && args
+                    .iter()
+                    .zip(fields)
+                    .all(|(arg, field)| bind(arg, field, bindings));
+        }
+        (Pattern::Some(_) | Pattern::Ok(_) | Pattern::Err(_) | Pattern::Constructor { .. }, _) => {
+            return false
+        }
+        (Pattern::Int(n), _) => Value::Int(*n),
+        (Pattern::Number(n), _) => Value::Number(*n),
+        (Pattern::Decimal(d), _) => Value::Decimal(*d),
+        (Pattern::String(s), _) => Value::String(s.clone()),
+        (Pattern::Boolean(b), _) => Value::Boolean(*b),
+        (Pattern::Unit, _) => Value::Unit,
+        (Pattern::None, _) => Value::Option(None),
+    };
+    matches!(equals(&literal, value), Ok(true))
+}
@@ -0,0 +1,132 @@
+use std::env;
+use std::path::{Component, Path, PathBuf};
+
+// Which files a capability reaches.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Access {
+    All,
+    Within(Vec<PathBuf>), // nothing when empty
+}
+
+impl Access {
+    fn allows(&self, path: &Path) -> bool {
+        match self {
+            Access::All => true,
+            Access::Within(dirs) => {
+                let path = resolve(path);
+                dirs.iter().any(|dir| path.starts_with(resolve(dir)))
+            }
+        }
+    }
+
+    fn add(&mut self, dir: PathBuf) {
+        match self {
+            Access::All => {}
+            Access::Within(dirs) => dirs.push(dir),
+        }
+    }
+}
+
+// What scripts may do outside the interpreter through builtins. Scripts
+// run unrestricted by default; `Sandbox::deny_all()` is the starting point
+// for running untrusted ones, extended with the capabilities they need.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sandbox {
+    pub read: Access,
+    pub write: Access,
+    pub env: bool,
+}
+
+impl Default for Sandbox {
+    fn default() -> Self {
+        Sandbox::unrestricted()
+    }
+}
+
+impl Sandbox {
+    pub fn unrestricted() -> Self {
+        Sandbox {
+            read: Access::All,
+            write: Access::All,
+            env: true,
+        }
+    }
+
+    pub fn deny_all() -> Self {
+        Sandbox {
+            read: Access::Within(vec![]),
+            write: Access::Within(vec![]),
+            env: false,
+        }
+    }
+
+    // Allows reading the files under `dir`.
+    pub fn allow_read(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.read.add(dir.into());
+        self
+    }
+
+    // Allows writing the files under `dir`.
+    pub fn allow_write(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.write.add(dir.into());
+        self
+    }
+
+    pub fn allow_env(mut self) -> Self {
+        self.env = true;
+        self
+    }
+
+    pub fn check_read(&self, path: &str) -> Result<(), String> {
+        check(&self.read, "read", path)
+    }
+
+    pub fn check_write(&self, path: &str) -> Result<(), String> {
+        check(&self.write, "write", path)
+    }
+
+    pub fn check_env(&self, name: &str) -> Result<(), String> {
+        if self.env {
+            Ok(())
+        } else {
+            Err(format!(
+                "Permission denied: cannot read environment variable {}",
+                name
+            ))
+        }
+    }
+}
+
+fn check(access: &Access, action: &str, path: &str) -> Result<(), String> {
+    if access.allows(Path::new(path)) {
+        Ok(())
+    } else {
+        Err(format!("Permission denied: cannot {} {}", action, path))
+    }
+}
+
+// The absolute form of `path` with symlinks, `.` and `..` resolved, so
+// that `data/../secret` is not mistaken for a file under `data`. Paths that
+// don't exist yet are resolved as far as they exist.
+fn resolve(path: &Path) -> PathBuf {
+    if let Ok(path) = path.canonicalize() {
+        return path;
+    }
+    let mut resolved = match env::current_dir() {
+        Ok(dir) if path.is_relative() => dir,
+        _ => PathBuf::new(),
+    };
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            _ => resolved.push(component),
+        }
+        if let Ok(canonical) = resolved.canonicalize() {
+            resolved = canonical;
+        }
+    }
+    resolved
+}
@@ -1,5 +1,59 @@
+use std::fmt;
+use std::rc::Rc;
+
+use rust_decimal::Decimal;
+
 use crate::ast::Expr;
+use crate::eval::context::Context;
 use crate::eval::env::Env;
+use crate::eval::error::Error;
+use crate::vm::vm::Closure;
+
+// The Rust code behind a builtin: one of the standard functions or a
+// closure registered by the application embedding the interpreter.
+#[derive(Clone)]
+pub struct NativeFn(Rc<NativeFnBody>);
+
+pub type NativeFnBody = dyn Fn(&Context, Vec<Value>) -> Result<Value, Error>;
+
+impl NativeFn {
+    pub fn new(func: impl Fn(&Context, Vec<Value>) -> Result<Value, Error> + 'static) -> Self {
+        NativeFn(Rc::new(func))
+    }
+
+    pub fn call(&self, ctx: &Context, args: Vec<Value>) -> Result<Value, Error> {
+        (self.0)(ctx, args)
+    }
+}
+
+impl fmt::Debug for NativeFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("NativeFn")
+    }
+}
+
+impl PartialEq for NativeFn {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+// A compiled regular expression. Two regexes are equal when their patterns
+// are.
+#[derive(Clone)]
+pub struct Regex(pub regex::Regex);
+
+impl fmt::Debug for Regex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Regex({:?})", self.0.as_str())
+    }
+}
+
+impl PartialEq for Regex {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_str() == other.0.as_str()
+    }
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct BuiltinFuncArgs {
@@ -9,19 +63,64 @@ pub struct BuiltinFuncArgs {
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Value {
+    Int(i64),
     Number(f64),
+    Decimal(Decimal),
     String(String),
     Boolean(bool),
-    List(Vec<Value>),
-    Record(std::collections::HashMap<String, Value>),
+    Unit,
+    Option(Option<Box<Value>>),
+    Result(Result<Box<Value>, Box<Value>>),
+    // A value of a user-defined type, tagged with its constructor's name.
+    Variant {
+        tag: String,
+        fields: Vec<Value>,
+    },
+    // Persistent collections: clones share structure, so passing values
+    // around and deriving new ones from them is cheap. Record fields are
+    // kept sorted by name, so they print and iterate the same on every run.
+    List(im_rc::Vector<Value>),
+    Record(im_rc::OrdMap<String, Value>),
+    Regex(Regex),
     Func {
+        name: String, // of the `let` binding defining it, `<lambda>` otherwise
         params: Vec<String>,
         body: Box<Expr>,
         env: Env,
+        curried: Vec<Value>,
     },
     BuiltinFunc {
         name: String,
-        func: fn(Vec<Value>) -> Result<Value, String>,
+        func: NativeFn,
         args: BuiltinFuncArgs,
     },
+    Closure(Rc<Closure>),
+}
+
+impl Value {
+    pub fn is_function(&self) -> bool {
+        matches!(
+            self,
+            Value::Func { .. } | Value::BuiltinFunc { .. } | Value::Closure(_)
+        )
+    }
+
+    // The name of the value's type as written in error messages.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Int(_) => "int",
+            Value::Number(_) => "float",
+            Value::Decimal(_) => "decimal",
+            Value::String(_) => "string",
+            Value::Boolean(_) => "bool",
+            Value::Unit => "unit",
+            Value::Option(_) => "option",
+            Value::Result(_) => "result",
+            Value::Variant { .. } => "variant",
+            Value::List(_) => "list",
+            Value::Record(_) => "record",
+            Value::Regex(_) => "regex",
+            Value::Func { .. } | Value::BuiltinFunc { .. } | Value::Closure(_) => "function",
+        }
+    }
 }
@@ -0,0 +1,268 @@
+use crate::ast::{Stmt, AST};
+use crate::eval::context::{Context, Io};
+use crate::eval::env::Env;
+use crate::eval::error::{Error, ErrorKind};
+use crate::eval::eval::{eval, eval_expr};
+use crate::eval::limits::Limits;
+use crate::eval::sandbox::Sandbox;
+use crate::eval::value::{BuiltinFuncArgs, NativeFn, Value};
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use crate::resolver::Resolver;
+use crate::token::TokenType;
+use crate::vm::vm::Vm;
+
+// A function the embedding application adds to the language. Scripts call
+// it like any builtin, currying included.
+#[derive(Debug, Clone)]
+pub struct Native {
+    pub name: String,
+    pub arity: usize,
+    pub description: String,
+    pub func: NativeFn,
+}
+
+impl Native {
+    pub fn to_value(&self) -> Value {
+        Value::BuiltinFunc {
+            name: self.name.clone(),
+            func: self.func.clone(),
+            args: BuiltinFuncArgs {
+                length: self.arity,
+                curried: vec![],
+            },
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct InterpreterBuilder {
+    natives: Vec<Native>,
+    globals: Vec<(String, Value)>,
+    io: Option<Io>,
+    sandbox: Option<Sandbox>,
+    limits: Option<Limits>,
+    use_vm: bool,
+}
+
+impl InterpreterBuilder {
+    // Registers a native function taking `arity` arguments. `description`
+    // is shown by editors, like the descriptions of the builtins.
+    pub fn function(
+        mut self,
+        name: &str,
+        arity: usize,
+        description: &str,
+        func: impl Fn(&Context, Vec<Value>) -> Result<Value, Error> + 'static,
+    ) -> Self {
+        self.natives.retain(|native| native.name != name);
+        self.natives.push(Native {
+            name: name.to_string(),
+            arity,
+            description: description.to_string(),
+            func: NativeFn::new(func),
+        });
+        self
+    }
+
+    // Binds a global scripts can read, e.g. configuration or input data.
+    pub fn global(mut self, name: &str, value: impl Into<Value>) -> Self {
+        self.globals.push((name.to_string(), value.into()));
+        self
+    }
+
+    // Streams used by `print`, `read_line` and the like instead of the
+    // process' standard ones.
+    pub fn io(mut self, io: Io) -> Self {
+        self.io = Some(io);
+        self
+    }
+
+    // Limits what scripts may read, write and look up through builtins,
+    // e.g. `Sandbox::deny_all().allow_read("./data")`.
+    pub fn sandbox(mut self, sandbox: Sandbox) -> Self {
+        self.sandbox = Some(sandbox);
+        self
+    }
+
+    // Caps the resources each run may use. Runs exceeding them fail with
+    // a `limit` error.
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = Some(limits);
+        self
+    }
+
+    // Runs scripts on the bytecode VM instead of the tree-walking evaluator.
+    pub fn vm(mut self, use_vm: bool) -> Self {
+        self.use_vm = use_vm;
+        self
+    }
+
+    pub fn build(self) -> Interpreter {
+        let mut io = self.io.unwrap_or_default();
+        if let Some(sandbox) = self.sandbox {
+            io = io.with_sandbox(sandbox);
+        }
+        if let Some(limits) = self.limits {
+            io = io.with_limits(limits);
+        }
+        let mut engine = if self.use_vm {
+            Engine::Vm(Vm::with_io(io))
+        } else {
+            Engine::Tree(Env::with_io(io))
+        };
+        for native in &self.natives {
+            engine.define(&native.name, native.to_value());
+        }
+        for (name, value) in self.globals {
+            engine.define(&name, value);
+        }
+        Interpreter {
+            engine,
+            natives: self.natives,
+        }
+    }
+}
+
+enum Engine {
+    Tree(Env),
+    Vm(Vm),
+}
+
+impl Engine {
+    fn define(&mut self, name: &str, value: Value) {
+        match self {
+            Engine::Tree(env) => env.set(name.to_string(), value),
+            Engine::Vm(vm) => vm.define(name, value),
+        }
+    }
+
+    fn get(&self, name: &str) -> Option<Value> {
+        match self {
+            Engine::Tree(env) => env.get(name).cloned(),
+            Engine::Vm(vm) => vm.get(name),
+        }
+    }
+
+    fn names(&self) -> Vec<String> {
+        match self {
+            Engine::Tree(env) => env.names(),
+            Engine::Vm(vm) => vm.names(),
+        }
+    }
+
+    fn io(&self) -> &Io {
+        match self {
+            Engine::Tree(env) => env.io(),
+            Engine::Vm(vm) => vm.io(),
+        }
+    }
+}
+
+// Runs Dolang source on behalf of an application. Definitions made by one
+// run are visible to the next ones.
+pub struct Interpreter {
+    engine: Engine,
+    natives: Vec<Native>,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+impl Interpreter {
+    pub fn builder() -> InterpreterBuilder {
+        InterpreterBuilder::default()
+    }
+
+    // The functions registered by the application.
+    pub fn natives(&self) -> &[Native] {
+        &self.natives
+    }
+
+    // Binds a global, replacing any previous binding of the name.
+    pub fn set_global(&mut self, name: &str, value: impl Into<Value>) {
+        self.engine.define(name, value.into());
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        self.engine.get(name)
+    }
+
+    pub fn run(&mut self, source: &str) -> Result<(), Error> {
+        let ast = self.parse(source)?;
+        self.engine.io().budget.reset();
+        match &mut self.engine {
+            Engine::Tree(env) => eval(ast, env),
+            Engine::Vm(vm) => vm.run(&ast),
+        }
+    }
+
+    // Runs `source` and returns the value of its last statement when that
+    // is an expression, unit otherwise.
+    pub fn eval_str(&mut self, source: &str) -> Result<Value, Error> {
+        let mut ast = self.parse(source)?;
+        self.engine.io().budget.reset();
+        let last = match ast.stmts.pop() {
+            Some(Stmt::Expr(expr)) => Some(expr),
+            Some(stmt) => {
+                ast.stmts.push(stmt);
+                None
+            }
+            None => None,
+        };
+        match &mut self.engine {
+            Engine::Tree(env) => {
+                eval(ast, env)?;
+                match last {
+                    Some(expr) => eval_expr(&expr, env),
+                    None => Ok(Value::Unit),
+                }
+            }
+            Engine::Vm(vm) => {
+                vm.run(&ast)?;
+                match last {
+                    Some(expr) => vm.eval_expr(&expr),
+                    None => Ok(Value::Unit),
+                }
+            }
+        }
+    }
+
+    // Calls the function bound to the global `name`.
+    pub fn call(&self, name: &str, args: Vec<Value>) -> Result<Value, Error> {
+        let func = self
+            .get_global(name)
+            .ok_or_else(|| format!("Undefined variable: {}", name))?;
+        self.apply(&func, args)
+    }
+
+    // Applies a function value obtained from a script, e.g. a lambda
+    // returned by `eval_str`. Missing arguments give back a function
+    // waiting for the rest.
+    pub fn apply(&self, func: &Value, args: Vec<Value>) -> Result<Value, Error> {
+        let io = self.engine.io();
+        io.budget.reset();
+        Context::new(io, None).apply(func, args)
+    }
+
+    fn parse(&self, source: &str) -> Result<AST, Error> {
+        let mut lexer = Lexer::new(source);
+        let mut tokens = Vec::new();
+        loop {
+            let token = lexer.next_token();
+            if token.token_type == TokenType::EOF {
+                break;
+            }
+            tokens.push(token);
+        }
+        let syntax_error = |message: String| Error::new(ErrorKind::Syntax, message);
+        let mut ast = Parser::new(tokens).parse().map_err(syntax_error)?;
+        Resolver::new(self.engine.names())
+            .resolve(&mut ast)
+            .map_err(syntax_error)?;
+        Ok(ast)
+    }
+}
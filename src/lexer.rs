@@ -1,4 +1,6 @@
-use crate::token::{Position, Range, Token, TokenType};
+use rust_decimal::Decimal;
+
+use crate::token::{Position, Range, StringPart, Token, TokenType};
 
 pub struct Lexer {
     input: Vec<char>,
@@ -41,11 +43,17 @@ impl Lexer {
 
         let current_char = self.current_char();
 
+        if current_char == 'r' && self.peek_char() == Some('"') {
+            self.consume(1); // Consume 'r'
+            return self.read_string(true);
+        }
+
         if current_char.is_alphabetic() || current_char == '_' {
             let identifier = self.read_identifier();
             return match identifier.as_str() {
                 "_" => self.token(TokenType::Underscore),
                 "let" => self.token(TokenType::Let),
+                "type" => self.token(TokenType::Type),
                 "if" => self.token(TokenType::If),
                 "fn" => self.token(TokenType::Fn),
                 "then" => self.token(TokenType::Then),
@@ -54,19 +62,22 @@ impl Lexer {
                 "in" => self.token(TokenType::In),
                 "is" => self.token(TokenType::Is),
                 "match" => self.token(TokenType::Match),
+                "try" => self.token(TokenType::Try),
+                "with" => self.token(TokenType::With),
                 "return" => self.token(TokenType::Return),
                 "and" => self.token(TokenType::And),
                 "or" => self.token(TokenType::Or),
                 "not" => self.token(TokenType::Not),
                 "true" => self.token(TokenType::True),
                 "false" => self.token(TokenType::False),
+                "none" => self.token(TokenType::None),
                 _ => self.token(TokenType::Identifier(identifier)),
             };
         }
 
         if current_char.is_ascii_digit() {
             let number = self.read_number();
-            return self.token(TokenType::Number(number.parse::<f64>().unwrap()));
+            return self.number_token(number);
         }
 
         match current_char {
@@ -82,7 +93,7 @@ impl Lexer {
                     if c.is_ascii_digit() {
                         self.consume(1);
                         let number = self.read_number();
-                        self.token(TokenType::Number(number.parse::<f64>().unwrap() * -1.0))
+                        self.number_token(format!("-{}", number))
                     } else {
                         self.consume(1);
                         self.token(TokenType::Minus)
@@ -97,8 +108,13 @@ impl Lexer {
                 self.token(TokenType::Asterisk)
             }
             '/' => {
-                self.consume(1);
-                self.token(TokenType::Slash)
+                if let Some('/') = self.peek_char() {
+                    self.consume(2);
+                    self.token(TokenType::SlashSlash)
+                } else {
+                    self.consume(1);
+                    self.token(TokenType::Slash)
+                }
             }
             '%' => {
                 self.consume(1);
@@ -181,56 +197,217 @@ impl Lexer {
                 while self.position < self.input.len() && self.current_char() != '\n' {
                     self.consume(1);
                 }
-                return self.next_token();
+                self.next_token()
             }
-            '"' => {
+            '"' => self.read_string(false),
+            _ => {
                 self.consume(1);
-                let mut result = String::new();
+                self.token(TokenType::Invalid)
+            }
+        }
+    }
 
-                while self.position < self.input.len() && self.current_char() != '"' {
-                    let c = self.current_char();
-                    if c == '\\' {
-                        // Escape sequence
-                        self.consume(1);
-                        if self.position >= self.input.len() {
-                            return self.token(TokenType::Invalid);
-                        }
-                        let esc = self.current_char();
-                        let escaped_char = match esc {
-                            'n' => '\n',
-                            't' => '\t',
-                            'r' => '\r',
-                            '\\' => '\\',
-                            '"' => '"',
-                            '0' => '\0',
-                            _ => {
-                                return self.token(TokenType::Invalid);
-                            }
-                        };
-                        result.push(escaped_char);
-                    } else {
-                        result.push(c);
-                    }
-                    self.consume(1);
-                }
+    // Lexes a string literal from its opening quote. Tripled quotes start a
+    // multi-line string, whose common indentation is stripped. Raw strings
+    // take backslashes and braces as they are.
+    fn read_string(&mut self, raw: bool) -> Token {
+        let triple = self.starts_with("\"\"\"");
+        self.consume(if triple { 3 } else { 1 });
 
-                if self.position < self.input.len() {
-                    self.consume(1); // Consume the closing quote
-                    self.token(TokenType::String(result))
-                } else {
-                    self.token(TokenType::Invalid)
+        let mut pieces = Vec::new();
+        // The text starts on the line after the opening quotes.
+        if triple && self.read_line_break() {
+            pieces.push(Piece::Indent(self.read_indent()));
+        }
+        let mut text = String::new();
+        let mut invalid = false;
+        loop {
+            if self.position >= self.input.len() {
+                return self.token(TokenType::UnterminatedString);
+            }
+            let c = self.current_char();
+            if triple && self.starts_with("\"\"\"") && !self.starts_with("\"\"\"\"") {
+                self.consume(3); // Consume the closing quotes
+                break;
+            }
+            if !triple && c == '"' {
+                self.consume(1); // Consume the closing quote
+                break;
+            }
+            if triple && self.read_line_break() {
+                if !text.is_empty() {
+                    pieces.push(Piece::Text(std::mem::take(&mut text)));
                 }
+                pieces.push(Piece::Newline);
+                pieces.push(Piece::Indent(self.read_indent()));
+                continue;
             }
-            _ => {
+            if raw {
+                text.push(c);
                 self.consume(1);
-                self.token(TokenType::Invalid)
+                continue;
+            }
+            if c == '{' {
+                self.consume(1); // Consume '{'
+                let start = self.start.clone();
+                let placeholder = self.read_placeholder();
+                self.start = start;
+                let Some(tokens) = placeholder else {
+                    return self.token(TokenType::UnterminatedString);
+                };
+                if !text.is_empty() {
+                    pieces.push(Piece::Text(std::mem::take(&mut text)));
+                }
+                pieces.push(Piece::Code(tokens));
+                continue;
+            }
+            if c == '\\' {
+                self.consume(1); // Consume '\'
+                if self.position >= self.input.len() {
+                    return self.token(TokenType::UnterminatedString);
+                }
+                match self.read_escape() {
+                    Some(escaped) => text.push(escaped),
+                    None => invalid = true, // the literal is lexed to its end
+                }
+                continue;
+            }
+            text.push(c);
+            self.consume(1);
+        }
+        if invalid {
+            return self.token(TokenType::Invalid);
+        }
+        if !text.is_empty() {
+            pieces.push(Piece::Text(text));
+        }
+
+        let margin = margin(&pieces);
+        // A closing line of only whitespace is not part of the text.
+        if let [.., Piece::Newline, Piece::Indent(_)] = pieces.as_slice() {
+            pieces.truncate(pieces.len() - 2);
+        }
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        for piece in pieces {
+            match piece {
+                Piece::Text(s) => text.push_str(&s),
+                Piece::Newline => text.push('\n'),
+                Piece::Indent(indent) => text.extend(indent.chars().skip(margin)),
+                Piece::Code(tokens) => {
+                    if !text.is_empty() {
+                        parts.push(StringPart::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(StringPart::Code(tokens));
+                }
             }
         }
+        if parts.is_empty() {
+            return self.token(TokenType::String(text));
+        }
+        if !text.is_empty() {
+            parts.push(StringPart::Text(text));
+        }
+        self.token(TokenType::Interpolated(parts))
+    }
+
+    // Consumes a line break in a string, if there is one.
+    fn read_line_break(&mut self) -> bool {
+        let len = if self.starts_with("\n") {
+            1
+        } else if self.starts_with("\r\n") {
+            2
+        } else {
+            return false;
+        };
+        self.consume(len);
+        true
+    }
+
+    fn read_indent(&mut self) -> String {
+        let mut indent = String::new();
+        while self.position < self.input.len() && matches!(self.current_char(), ' ' | '\t') {
+            indent.push(self.current_char());
+            self.consume(1);
+        }
+        indent
+    }
+
+    // Reads the escape sequence after a backslash, None when it is not a
+    // valid one.
+    fn read_escape(&mut self) -> Option<char> {
+        let escaped = match self.current_char() {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '\\' => '\\',
+            '"' => '"',
+            '0' => '\0',
+            '{' => '{',
+            '}' => '}',
+            'u' => {
+                self.consume(1); // Consume 'u'
+                return self.read_unicode_escape();
+            }
+            _ => return None,
+        };
+        self.consume(1);
+        Some(escaped)
+    }
+
+    // Reads the `{...}` of a `\u{...}` escape: the hexadecimal code point
+    // of a character, in one to six digits.
+    fn read_unicode_escape(&mut self) -> Option<char> {
+        if !self.starts_with("{") {
+            return None;
+        }
+        self.consume(1); // Consume '{'
+        let mut digits = String::new();
+        while self.position < self.input.len() && self.current_char().is_ascii_hexdigit() {
+            digits.push(self.current_char());
+            self.consume(1);
+        }
+        if !self.starts_with("}") || digits.is_empty() || digits.len() > 6 {
+            return None;
+        }
+        self.consume(1); // Consume '}'
+        u32::from_str_radix(&digits, 16)
+            .ok()
+            .and_then(char::from_u32)
+    }
+
+    // Lexes the code of a string placeholder up to the brace closing it,
+    // which is consumed. None when the input ends first.
+    fn read_placeholder(&mut self) -> Option<Vec<Token>> {
+        let mut tokens: Vec<Token> = Vec::new();
+        let mut depth = 0;
+        loop {
+            let token = self.next_token();
+            match token.token_type {
+                TokenType::EOF => {
+                    // The string ends with what was lexed, not at the EOF.
+                    if let Some(last) = tokens.last() {
+                        self.last = last.range.end.clone();
+                    }
+                    return None;
+                }
+                TokenType::LeftBrace => depth += 1,
+                TokenType::RightBrace if depth == 0 => return Some(tokens),
+                TokenType::RightBrace => depth -= 1,
+                _ => {}
+            }
+            tokens.push(token);
+        }
     }
 
     fn current_char(&self) -> char {
         self.input[self.position]
     }
+    fn starts_with(&self, s: &str) -> bool {
+        s.chars()
+            .enumerate()
+            .all(|(i, c)| self.input.get(self.position + i) == Some(&c))
+    }
     fn peek_char(&self) -> Option<char> {
         if self.position + 1 < self.input.len() {
             Some(self.input[self.position + 1])
@@ -276,6 +453,33 @@ impl Lexer {
         self.input[start_pos..self.position].iter().collect()
     }
 
+    // Literals with a `d` suffix are decimals, literals without a dot are
+    // integers. Integers that don't fit in 64 bits are invalid rather than
+    // silently rounded.
+    fn number_token(&mut self, number: String) -> Token {
+        let suffixed = self.position < self.input.len()
+            && self.current_char() == 'd'
+            && !self
+                .peek_char()
+                .is_some_and(|c| c.is_alphanumeric() || c == '_');
+        if suffixed {
+            self.consume(1); // Consume 'd'
+            return match Decimal::from_str_exact(&number) {
+                Ok(d) => self.token(TokenType::Decimal(d)),
+                Err(_) => self.token(TokenType::Invalid),
+            };
+        }
+
+        if number.contains('.') {
+            self.token(TokenType::Number(number.parse::<f64>().unwrap()))
+        } else {
+            match number.parse::<i64>() {
+                Ok(n) => self.token(TokenType::Int(n)),
+                Err(_) => self.token(TokenType::IntOutOfRange(number)),
+            }
+        }
+    }
+
     fn skip_whitespace(&mut self) {
         while self.position < self.input.len()
             && (self.current_char().is_whitespace() || self.current_char() == '\n')
@@ -315,3 +519,28 @@ impl Lexer {
         }
     }
 }
+
+// A piece of a string literal, before the indentation of multi-line
+// strings is stripped.
+enum Piece {
+    Text(String),
+    Code(Vec<Token>),
+    Newline,        // a line break in the source
+    Indent(String), // the whitespace starting a line
+}
+
+// The indentation common to the lines of a multi-line string that are not
+// blank, the line of its closing quotes included.
+fn margin(pieces: &[Piece]) -> usize {
+    let mut margin: Option<usize> = None;
+    for (i, piece) in pieces.iter().enumerate() {
+        if let Piece::Indent(indent) = piece {
+            let blank = matches!(pieces.get(i + 1), Some(Piece::Newline));
+            if !blank {
+                let width = indent.chars().count();
+                margin = Some(margin.map_or(width, |margin| margin.min(width)));
+            }
+        }
+    }
+    margin.unwrap_or(0)
+}
@@ -1,7 +1,14 @@
+#![allow(clippy::module_inception)]
+
 pub mod ast;
 pub mod debugger;
 pub mod eval;
+pub mod interpreter;
 pub mod lexer;
 pub mod lsp;
 pub mod parser;
+pub mod resolver;
 pub mod token;
+pub mod vm;
+
+pub use interpreter::{Interpreter, InterpreterBuilder};
@@ -8,6 +8,10 @@ pub const KEYWORDS: &[Keyword] = &[
         name: "let",
         description: "Declares a variable with a specified name and value.",
     },
+    Keyword {
+        name: "type",
+        description: "Declares a type with the constructors of its variants.",
+    },
     Keyword {
         name: "fn",
         description: "Defines a function with a specified name and parameters.",
@@ -36,6 +40,10 @@ pub const KEYWORDS: &[Keyword] = &[
         name: "match",
         description: "Pattern matching construct for handling different cases.",
     },
+    Keyword {
+        name: "try",
+        description: "Catches runtime errors, giving ok(value) or err({ kind, message }), or the fallback after 'else'.",
+    },
     Keyword {
         name: "and",
         description: "Logical operator that returns true if both operands are true.",
@@ -8,6 +8,7 @@ use tower_lsp::{Client, LanguageServer};
 
 use crate::ast::{Expr, Stmt};
 use crate::eval::builtin::builtin::BUILTIN_FUNCTIONS;
+use crate::interpreter::Native;
 use crate::lexer::Lexer;
 use crate::parser::Parser;
 
@@ -21,6 +22,12 @@ pub struct Backend {
 
 impl Backend {
     pub fn new(client: Client) -> Self {
+        Self::with_natives(client, &[])
+    }
+
+    // A server that also completes the functions an application registered
+    // on its interpreter.
+    pub fn with_natives(client: Client, natives: &[Native]) -> Self {
         let mut items = vec![];
         for func in BUILTIN_FUNCTIONS.iter() {
             items.push(CompletionItem {
@@ -31,7 +38,16 @@ impl Backend {
                 ..Default::default()
             });
         }
-        for value in ["true", "false"] {
+        for native in natives {
+            items.push(CompletionItem {
+                label: native.name.clone(),
+                kind: Some(CompletionItemKind::FUNCTION),
+                detail: Some(native.description.clone()),
+                documentation: None,
+                ..Default::default()
+            });
+        }
+        for value in ["true", "false", "none"] {
             items.push(CompletionItem {
                 label: value.to_string(),
                 kind: Some(CompletionItemKind::VALUE),
@@ -164,6 +180,20 @@ impl LanguageServer for Backend {
                         items.push(var);
                     }
                 },
+                Stmt::Type { name, variants } => {
+                    for variant in variants {
+                        items.push(CompletionItem {
+                            label: variant.name.clone(),
+                            kind: Some(CompletionItemKind::CONSTRUCTOR),
+                            detail: Some(format!(
+                                "Constructor of {} with fields: {:?}",
+                                name, variant.fields
+                            )),
+                            documentation: None,
+                            ..Default::default()
+                        });
+                    }
+                }
                 _ => {
                     // do nothing for other statements
                 }
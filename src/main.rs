@@ -1,25 +1,39 @@
 use dolang::debugger::ast::print_ast;
 use dolang::debugger::token::print_tokens;
+use dolang::eval::context::Io;
 use dolang::eval::env::Env;
+use dolang::eval::error::{Error, ErrorKind};
 use dolang::eval::eval::eval;
+use dolang::eval::limits::{Limits, DEFAULT_DEPTH, SCRIPT_STACK_SIZE};
+use dolang::eval::sandbox::{Access, Sandbox};
 use dolang::lsp::lsp::Backend;
+use dolang::resolver::Resolver;
+use dolang::vm::vm::Vm;
 use dolang::{lexer, parser, token};
 use std::io::{self, Write};
+use std::time::Duration;
 
 use tower_lsp::{LspService, Server};
 
 const VERSION: &str = "0.1.0";
 
+// Exit codes of `dolang <filename>`.
+const EXIT_ERROR: i32 = 1; // the script couldn't be parsed or raised an error
+const EXIT_ASSERTION: i32 = 2; // an `assert` failed
+
 #[tokio::main]
 async fn main() {
     let args: Vec<String> = std::env::args().collect();
 
     let mut debug = false;
+    let mut use_vm = false;
     let mut file_name = String::new();
     let mut repl = false;
     let mut lsp = false;
     let mut help = false;
     let mut version = false;
+    let mut sandbox: Option<Sandbox> = None;
+    let mut limits = Limits::default();
 
     for arg in args.iter().skip(1) {
         match arg.as_str() {
@@ -38,8 +52,30 @@ async fn main() {
             "-d" | "--debug" => {
                 debug = true;
             }
+            "--vm" => {
+                use_vm = true;
+            }
+            "--sandbox" => {
+                sandbox.get_or_insert_with(Sandbox::deny_all);
+            }
+            flag if flag.starts_with("--allow-") => {
+                let current = sandbox.take().unwrap_or_else(Sandbox::deny_all);
+                match allow(current, flag) {
+                    Ok(allowed) => sandbox = Some(allowed),
+                    Err(e) => {
+                        eprintln!("[!] {}", e);
+                        return;
+                    }
+                }
+            }
+            flag if flag.starts_with("--max-") || flag.starts_with("--timeout") => {
+                if let Err(e) = limit(&mut limits, flag) {
+                    eprintln!("[!] {}", e);
+                    return;
+                }
+            }
             _ => {
-                file_name = args[1].clone();
+                file_name = arg.clone();
             }
         }
     }
@@ -47,6 +83,7 @@ async fn main() {
     if debug {
         println!("[+] Debug mode enabled");
     }
+    let sandbox = sandbox.unwrap_or_default();
 
     if help {
         show_help();
@@ -61,7 +98,7 @@ async fn main() {
         return;
     }
     if repl {
-        run_repl(debug);
+        on_script_thread(move || run_repl(debug, use_vm, script_io(sandbox, limits)));
         return;
     }
 
@@ -73,10 +110,68 @@ async fn main() {
         eprintln!("[!] Invalid file extension. Please use a .dolang file.");
         return;
     }
-    run_file(&file_name, debug);
+    let code =
+        on_script_thread(move || run_file(&file_name, debug, use_vm, script_io(sandbox, limits)));
+    if code != 0 {
+        std::process::exit(code);
+    }
+}
+
+// Scripts get a thread with a large stack, for deep recursion to hit the
+// depth limit rather than overflow it.
+fn on_script_thread<T: Send + 'static>(run: impl FnOnce() -> T + Send + 'static) -> T {
+    let thread = std::thread::Builder::new()
+        .stack_size(SCRIPT_STACK_SIZE)
+        .spawn(run)
+        .expect("[!] Failed to start the script thread");
+    thread
+        .join()
+        .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+}
+
+fn script_io(sandbox: Sandbox, limits: Limits) -> Io {
+    Io::default().with_sandbox(sandbox).with_limits(limits)
+}
+
+// Applies an `--allow-...` flag to the sandbox. Without a list of
+// directories, reads and writes are allowed everywhere.
+fn allow(sandbox: Sandbox, flag: &str) -> Result<Sandbox, String> {
+    match flag.split_once('=') {
+        None if flag == "--allow-read" => Ok(Sandbox {
+            read: Access::All,
+            ..sandbox
+        }),
+        None if flag == "--allow-write" => Ok(Sandbox {
+            write: Access::All,
+            ..sandbox
+        }),
+        None if flag == "--allow-env" => Ok(sandbox.allow_env()),
+        Some(("--allow-read", dirs)) => Ok(dirs.split(',').fold(sandbox, Sandbox::allow_read)),
+        Some(("--allow-write", dirs)) => Ok(dirs.split(',').fold(sandbox, Sandbox::allow_write)),
+        _ => Err(format!("Unknown option: {}", flag)),
+    }
+}
+
+// Applies a `--max-...=<n>` or `--timeout=<seconds>` flag to the limits.
+fn limit(limits: &mut Limits, flag: &str) -> Result<(), String> {
+    let invalid = || format!("Invalid value for {}", flag);
+    let (name, value) = flag.split_once('=').ok_or_else(invalid)?;
+    match name {
+        "--max-steps" => limits.steps = Some(value.parse().map_err(|_| invalid())?),
+        "--max-depth" => limits.depth = Some(value.parse().map_err(|_| invalid())?),
+        "--max-list" => limits.list_len = Some(value.parse().map_err(|_| invalid())?),
+        "--max-values" => limits.values = Some(value.parse().map_err(|_| invalid())?),
+        "--timeout" => {
+            let seconds: f64 = value.parse().map_err(|_| invalid())?;
+            limits.timeout = Some(Duration::try_from_secs_f64(seconds).map_err(|_| invalid())?);
+        }
+        _ => return Err(format!("Unknown option: {}", flag)),
+    }
+    Ok(())
 }
 
-fn run_file(filename: &str, debug: bool) {
+// Runs a script and returns the process exit code.
+fn run_file(filename: &str, debug: bool, use_vm: bool, io: Io) -> i32 {
     let source = std::fs::read_to_string(filename).expect("[!] Failed to read file");
     let mut lexer = lexer::Lexer::new(&source);
     let mut tokens = Vec::new();
@@ -92,25 +187,55 @@ fn run_file(filename: &str, debug: bool) {
     }
 
     let mut parser = parser::Parser::new(tokens);
-    let ast = match parser.parse() {
+    let mut ast = match parser.parse() {
         Ok(ast) => ast,
         Err(e) => {
             eprintln!("[!] Error parsing input: {}", e);
-            return;
+            return EXIT_ERROR;
         }
     };
+
+    let mut env = Env::with_io(io.clone());
+    if let Err(e) = Resolver::new(env.names()).resolve(&mut ast) {
+        eprintln!("[!] Error resolving input: {}", e);
+        return EXIT_ERROR;
+    }
     if debug {
         print_ast(&ast);
     }
 
-    eval(ast, &mut Env::new(None)).unwrap_or_else(|e| {
+    let result = if use_vm {
+        Vm::with_io(io).run(&ast)
+    } else {
+        eval(ast, &mut env)
+    };
+    let Err(e) = result else {
+        return 0;
+    };
+    if e.kind == ErrorKind::Assertion {
+        eprintln!("[!] Assertion failed: {}", e);
+    } else {
         eprintln!("[!] Error evaluating input: {}", e);
-    });
+    }
+    print_backtrace(&e);
+    if e.kind == ErrorKind::Assertion {
+        EXIT_ASSERTION
+    } else {
+        EXIT_ERROR
+    }
 }
 
-fn run_repl(debug: bool) {
+fn print_backtrace(e: &Error) {
+    if !e.trace.is_empty() {
+        eprintln!("Backtrace (innermost call first):");
+        eprintln!("{}", e.backtrace());
+    }
+}
+
+fn run_repl(debug: bool, use_vm: bool, io: Io) {
     println!("[*] Welcome to Dolang :)");
-    let mut env = Env::new(None);
+    let mut env = Env::with_io(io.clone());
+    let mut vm = Vm::with_io(io.clone());
 
     loop {
         print!("repl> ");
@@ -146,19 +271,33 @@ fn run_repl(debug: bool) {
         }
 
         let mut parser = parser::Parser::new(tokens);
-        let ast = match parser.parse() {
+        let mut ast = match parser.parse() {
             Ok(ast) => ast,
             Err(e) => {
                 eprintln!("Error parsing input: {}", e);
                 continue; // Skip to the next iteration on error
             }
         };
+
+        let globals = if use_vm { vm.names() } else { env.names() };
+        if let Err(e) = Resolver::new(globals).resolve(&mut ast) {
+            eprintln!("Error resolving input: {}", e);
+            continue;
+        }
         if debug {
             println!("Parsed AST: {:?}", ast);
         }
 
-        eval(ast, &mut env).unwrap_or_else(|e| {
+        // Every input gets the full limits.
+        io.budget.reset();
+        let result = if use_vm {
+            vm.run(&ast)
+        } else {
+            eval(ast, &mut env)
+        };
+        result.unwrap_or_else(|e| {
             eprintln!("Error evaluating input: {}", e);
+            print_backtrace(&e);
         });
     }
 }
@@ -167,7 +306,7 @@ async fn run_lsp() {
     let stdin = tokio::io::stdin();
     let stdout = tokio::io::stdout();
 
-    let (service, socket) = LspService::new(|client| Backend::new(client));
+    let (service, socket) = LspService::new(Backend::new);
     Server::new(stdin, stdout, socket).serve(service).await;
 }
 
@@ -179,6 +318,27 @@ fn show_help() {
     println!("  l, lsp: Start the Dolang Language Server Protocol (LSP)");
     println!("  h, help: Show this help message");
     println!("  v, version: Show the version of Dolang");
+    println!("Options:");
+    println!("  -d, --debug: Print tokens and AST before running");
+    println!("  --vm: Run on the bytecode virtual machine instead of the tree-walking evaluator");
+    println!("  --sandbox: Deny scripts access to files and environment variables");
+    println!("  --allow-read[=<dirs>]: Sandbox, but allow reading files (under the comma-separated directories)");
+    println!("  --allow-write[=<dirs>]: Sandbox, but allow writing files (under the comma-separated directories)");
+    println!("  --allow-env: Sandbox, but allow reading environment variables");
+    println!("  --max-steps=<n>: Stop scripts after n evaluation steps");
+    println!(
+        "  --max-depth=<n>: Stop scripts nesting function calls more than n deep (default {})",
+        DEFAULT_DEPTH
+    );
+    println!("  --max-list=<n>: Stop scripts building lists of more than n elements");
+    println!("  --max-values=<n>: Stop scripts after creating n list elements and record fields");
+    println!("  --timeout=<seconds>: Stop scripts running longer than this");
+    println!("Exit codes:");
+    println!(
+        "  {}: The script failed to parse or raised an error",
+        EXIT_ERROR
+    );
+    println!("  {}: An assertion failed", EXIT_ASSERTION);
 }
 
 fn show_version() {
@@ -1,5 +1,7 @@
-use crate::ast::{Case, CompOp, Expr, FactorOp, LogicOp, Pattern, Stmt, TermOp, UnaryOp, AST};
-use crate::token::{Token, TokenType};
+use crate::ast::{
+    Case, CompOp, Expr, FactorOp, LogicOp, Pattern, Segment, Stmt, TermOp, UnaryOp, Variant, AST,
+};
+use crate::token::{Range, StringPart, Token, TokenType};
 
 pub struct Parser {
     tokens: Vec<Token>,
@@ -30,8 +32,23 @@ impl Parser {
     fn next(&mut self) {
         self.position += 1;
     }
+    fn current_range(&self) -> Option<&Range> {
+        self.tokens.get(self.position).map(|t| &t.range)
+    }
+    fn previous_range(&self) -> Option<&Range> {
+        let position = self.position.checked_sub(1)?;
+        self.tokens.get(position).map(|t| &t.range)
+    }
+    // Field names can be keywords too, e.g. `row.type`.
+    fn field_name(&self) -> Option<String> {
+        match self.current_token_type() {
+            TokenType::Identifier(name) => Some(name.clone()),
+            token => token.keyword().map(str::to_string),
+        }
+    }
 
     pub fn parse(&mut self) -> Result<AST, String> {
+        check_literals(&self.tokens)?;
         let mut stmts = Vec::new();
         while self.current_token_type() != &TokenType::EOF {
             match self.parse_statement() {
@@ -51,6 +68,7 @@ impl Parser {
     fn parse_statement(&mut self) -> Result<Stmt, String> {
         match self.current_token_type() {
             TokenType::Let => self.parse_let_stmt(),
+            TokenType::Type => self.parse_type_stmt(),
             _ => self.parse_expr_stmt(),
         }
     }
@@ -71,6 +89,63 @@ impl Parser {
         let val = self.parse_expr()?;
         Ok(Stmt::Let { name, val })
     }
+    fn parse_type_stmt(&mut self) -> Result<Stmt, String> {
+        self.next(); // Consume 'type'
+
+        let name = match self.current_token_type() {
+            TokenType::Identifier(id) => id.clone(),
+            _ => return Err("Expected identifier after 'type'".into()),
+        };
+        self.next(); // Consume identifier
+
+        if self.current_token_type() != &TokenType::Equal {
+            return Err("Expected '=' after type name".into());
+        }
+        self.next(); // Consume '='
+
+        // The first variant may be preceded by '|' as well
+        if self.current_token_type() == &TokenType::Pipe {
+            self.next(); // Consume '|'
+        }
+
+        let mut variants = Vec::new();
+        loop {
+            let variant_name = match self.current_token_type() {
+                TokenType::Identifier(id) if is_constructor(id) => id.clone(),
+                _ => return Err("Expected a capitalized constructor name".into()),
+            };
+            self.next(); // Consume constructor name
+
+            let mut fields = Vec::new();
+            if self.current_token_type() == &TokenType::LeftParen {
+                self.next(); // Consume '('
+                while let TokenType::Identifier(field) = self.current_token_type() {
+                    fields.push(field.clone());
+                    self.next(); // Consume field name
+                    if self.current_token_type() == &TokenType::Comma {
+                        self.next(); // Consume ','
+                    } else {
+                        break;
+                    }
+                }
+                if self.current_token_type() != &TokenType::RightParen {
+                    return Err("Expected ')' after constructor fields".into());
+                }
+                self.next(); // Consume ')'
+            }
+            variants.push(Variant {
+                name: variant_name,
+                fields,
+            });
+
+            if self.current_token_type() != &TokenType::Pipe {
+                break;
+            }
+            self.next(); // Consume '|'
+        }
+
+        Ok(Stmt::Type { name, variants })
+    }
     fn parse_expr_stmt(&mut self) -> Result<Stmt, String> {
         let expr = self.parse_expr()?;
         Ok(Stmt::Expr(expr))
@@ -92,11 +167,11 @@ impl Parser {
             self.next(); // Consume 'else'
             let else_branch = self.parse_expr()?;
 
-            return Ok(Expr::If {
+            Ok(Expr::If {
                 cond: Box::new(cond),
                 then: Box::new(then_branch),
                 else_: Box::new(else_branch),
-            });
+            })
         } else if self.current_token_type() == &TokenType::Match {
             self.next(); // Consume 'match'
 
@@ -106,15 +181,7 @@ impl Parser {
             while self.current_token_type() == &TokenType::Pipe {
                 self.next(); // Consume '|'
 
-                let pattern = match self.current_token_type() {
-                    TokenType::Number(n) => Pattern::Number(*n),
-                    TokenType::String(s) => Pattern::String(s.clone()),
-                    TokenType::True => Pattern::Boolean(true),
-                    TokenType::False => Pattern::Boolean(false),
-                    TokenType::Underscore => Pattern::Wildcard,
-                    _ => return Err("Expected pattern after '|'".into()),
-                };
-                self.next(); // Consume pattern
+                let pattern = self.parse_pattern()?;
 
                 if self.current_token_type() != &TokenType::Arrow {
                     return Err("Expected '->' after pattern".into());
@@ -127,10 +194,24 @@ impl Parser {
             if cases.is_empty() {
                 return Err("Expected at least one case after 'match'".into());
             }
-            return Ok(Expr::Match {
+            Ok(Expr::Match {
                 cond: Box::new(cond),
                 cases,
-            });
+            })
+        } else if self.current_token_type() == &TokenType::Try {
+            self.next(); // Consume 'try'
+            let body = self.parse_expr()?;
+
+            let mut fallback = None;
+            if self.current_token_type() == &TokenType::Else {
+                self.next(); // Consume 'else'
+                fallback = Some(Box::new(self.parse_expr()?));
+            }
+
+            Ok(Expr::Try {
+                body: Box::new(body),
+                fallback,
+            })
         } else if self.current_token_type() == &TokenType::Fn {
             self.next(); // Consume 'fn'
 
@@ -160,12 +241,12 @@ impl Parser {
             self.next(); // Consume '->'
 
             let body = self.parse_expr()?;
-            return Ok(Expr::Func {
+            Ok(Expr::Func {
                 params,
                 body: Box::new(body),
-            });
+            })
         } else {
-            return self.parse_pipe_expr();
+            self.parse_pipe_expr()
         }
     }
     fn parse_pipe_expr(&mut self) -> Result<Expr, String> {
@@ -174,11 +255,17 @@ impl Parser {
         while self.current_token_type() == &TokenType::ForwardPipe {
             self.next(); // Consume '|>'
 
+            let start = self.current_range().map(|r| r.start.clone());
             let right = self.parse_logic_expr()?;
+            let end = self.previous_range().map(|r| r.end.clone());
 
             expr = Expr::Pipe {
                 left: Box::new(expr),
                 right: Box::new(right),
+                span: Range {
+                    start: start.expect("pipe stage has a range"),
+                    end: end.expect("pipe stage has a range"),
+                },
             };
         }
 
@@ -287,12 +374,18 @@ impl Parser {
     }
     fn parse_factor_expr(&mut self) -> Result<Expr, String> {
         let left = self.parse_unary_expr()?;
-        if [TokenType::Asterisk, TokenType::Slash, TokenType::Percent]
-            .contains(self.current_token_type())
+        if [
+            TokenType::Asterisk,
+            TokenType::Slash,
+            TokenType::SlashSlash,
+            TokenType::Percent,
+        ]
+        .contains(self.current_token_type())
         {
             let op = match self.current_token_type() {
                 TokenType::Asterisk => FactorOp::Multiply,
                 TokenType::Slash => FactorOp::Divide,
+                TokenType::SlashSlash => FactorOp::IntDivide,
                 TokenType::Percent => FactorOp::Modulus,
                 _ => unreachable!(),
             };
@@ -332,6 +425,7 @@ impl Parser {
         let curr_tok = self.current_token_type().clone();
         match curr_tok {
             TokenType::Identifier(id) => {
+                let start = self.current_range().map(|r| r.start.clone());
                 self.next(); // Consume identifier
 
                 match self.current_token_type() {
@@ -379,10 +473,10 @@ impl Parser {
                         } else {
                             Expr::Index {
                                 list: Box::new(Expr::Identifier(id.clone())),
-                                index: start.ok_or_else(|| "Expected index for list access")?,
+                                index: start.ok_or("Expected index for list access")?,
                             }
                         };
-                        return Ok(list_access);
+                        Ok(list_access)
                     }
                     // function call
                     TokenType::LeftParen => {
@@ -401,41 +495,63 @@ impl Parser {
                         if self.current_token_type() != &TokenType::RightParen {
                             return Err("Expected ')'".into());
                         }
+                        let end = self.current_range().map(|r| r.end.clone());
                         self.next(); // Consume ')'
 
-                        return Ok(Expr::Call {
+                        Ok(Expr::Call {
                             name: Box::new(Expr::Identifier(id.clone())),
                             args,
-                        });
+                            span: Range {
+                                start: start.expect("identifier has a range"),
+                                end: end.expect("')' has a range"),
+                            },
+                        })
                     }
                     // record access
                     TokenType::Dot => {
                         self.next(); // Consume '.'
 
-                        let field = match self.current_token_type() {
-                            TokenType::Identifier(field_name) => field_name.clone(),
-                            _ => return Err("Expected identifier after '.'".into()),
+                        let field = match self.field_name() {
+                            Some(field_name) => field_name,
+                            None => return Err("Expected identifier after '.'".into()),
                         };
-                        self.next(); // Consume identifier
+                        self.next(); // Consume field name
 
-                        return Ok(Expr::Access {
+                        Ok(Expr::Access {
                             record: Box::new(Expr::Identifier(id.clone())),
                             field,
-                        });
-                    }
-                    _ => {
-                        return Ok(Expr::Identifier(id.clone()));
+                        })
                     }
+                    _ => Ok(Expr::Identifier(id.clone())),
                 }
             }
+            TokenType::Int(n) => {
+                self.next(); // Consume integer
+                Ok(Expr::Int(n))
+            }
             TokenType::Number(n) => {
                 self.next(); // Consume number
                 Ok(Expr::Number(n))
             }
+            TokenType::Decimal(d) => {
+                self.next(); // Consume decimal
+                Ok(Expr::Decimal(d))
+            }
             TokenType::String(s) => {
                 self.next(); // Consume string
                 Ok(Expr::String(s.clone()))
             }
+            TokenType::Interpolated(parts) => {
+                self.next(); // Consume string
+                let mut segments = Vec::new();
+                for part in parts {
+                    segments.push(match part {
+                        StringPart::Text(text) => Segment::Text(text),
+                        StringPart::Code(tokens) => Segment::Expr(parse_placeholder(tokens)?),
+                    });
+                }
+                Ok(Expr::Interpolated(segments))
+            }
             TokenType::True => {
                 self.next(); // Consume 'true'
                 Ok(Expr::Boolean(true))
@@ -444,9 +560,19 @@ impl Parser {
                 self.next(); // Consume 'false'
                 Ok(Expr::Boolean(false))
             }
+            TokenType::None => {
+                self.next(); // Consume 'none'
+                Ok(Expr::None)
+            }
             TokenType::LeftParen => {
                 self.next(); // Consume '('
 
+                // unit
+                if self.current_token_type() == &TokenType::RightParen {
+                    self.next(); // Consume ')'
+                    return Ok(Expr::Unit);
+                }
+
                 let expr = self.parse_expr()?;
 
                 if self.current_token_type() != &TokenType::RightParen {
@@ -476,38 +602,30 @@ impl Parser {
 
                 Ok(Expr::List(elements))
             }
-            // record
+            // record, or record update `{ record with field: value }`
             TokenType::LeftBrace => {
                 self.next(); // Consume '{'
 
-                let mut fields = Vec::new();
-                while self.current_token_type() != &TokenType::RightBrace {
-                    let curr_tok = self.current_token_type().clone();
-                    match curr_tok {
-                        TokenType::Identifier(field_name) => {
-                            self.next(); // Consume field name
-                            if self.current_token_type() != &TokenType::Colon {
-                                return Err("Expected ':' after field name".into());
-                            }
-                            self.next(); // Consume ':'
+                let literal = self.current_token_type() == &TokenType::RightBrace
+                    || (self.field_name().is_some() && self.next_token_type() == &TokenType::Colon);
+                if literal {
+                    let fields = self.parse_fields()?;
+                    return Ok(Expr::Record(fields));
+                }
 
-                            let field_value = self.parse_expr()?;
-                            fields.push((field_name.clone(), field_value));
-                        }
-                        _ => return Err("Expected identifier for field name".into()),
-                    }
-                    if self.current_token_type() == &TokenType::Comma {
-                        self.next(); // Consume ','
-                    } else {
-                        break;
-                    }
+                let record = self.parse_expr()?;
+                if self.current_token_type() != &TokenType::With {
+                    return Err("Expected 'with' after the record to update".into());
                 }
-                if self.current_token_type() != &TokenType::RightBrace {
-                    return Err("Expected '}'".into());
+                self.next(); // Consume 'with'
+                if self.current_token_type() == &TokenType::RightBrace {
+                    return Err("Expected fields to update after 'with'".into());
                 }
-                self.next(); // Consume '}'
-
-                return Ok(Expr::Record(fields));
+                let fields = self.parse_fields()?;
+                Ok(Expr::Update {
+                    record: Box::new(record),
+                    fields,
+                })
             }
             _ => Err(format!(
                 "Expected identifier, number, string, true, false, or '(' but found: {:?}",
@@ -515,4 +633,130 @@ impl Parser {
             )),
         }
     }
+
+    // `field: value, ...}`, the fields of a record up to its closing brace.
+    fn parse_fields(&mut self) -> Result<Vec<(String, Expr)>, String> {
+        let mut fields = Vec::new();
+        while self.current_token_type() != &TokenType::RightBrace {
+            match self.field_name() {
+                Some(field_name) => {
+                    self.next(); // Consume field name
+                    if self.current_token_type() != &TokenType::Colon {
+                        return Err("Expected ':' after field name".into());
+                    }
+                    self.next(); // Consume ':'
+
+                    let field_value = self.parse_expr()?;
+                    fields.push((field_name, field_value));
+                }
+                None => return Err("Expected identifier for field name".into()),
+            }
+            if self.current_token_type() == &TokenType::Comma {
+                self.next(); // Consume ','
+            } else {
+                break;
+            }
+        }
+        if self.current_token_type() != &TokenType::RightBrace {
+            return Err("Expected '}'".into());
+        }
+        self.next(); // Consume '}'
+
+        Ok(fields)
+    }
+
+    fn parse_pattern(&mut self) -> Result<Pattern, String> {
+        let pattern = match self.current_token_type().clone() {
+            TokenType::Int(n) => Pattern::Int(n),
+            TokenType::Number(n) => Pattern::Number(n),
+            TokenType::Decimal(d) => Pattern::Decimal(d),
+            TokenType::String(s) => Pattern::String(s),
+            TokenType::True => Pattern::Boolean(true),
+            TokenType::False => Pattern::Boolean(false),
+            TokenType::None => Pattern::None,
+            TokenType::Underscore => Pattern::Wildcard,
+            TokenType::LeftParen if self.next_token_type() == &TokenType::RightParen => {
+                self.next(); // Consume '('
+                Pattern::Unit
+            }
+            // some(p), ok(p), err(p)
+            TokenType::Identifier(name)
+                if ["some", "ok", "err"].contains(&name.as_str())
+                    && self.next_token_type() == &TokenType::LeftParen =>
+            {
+                self.next(); // Consume name
+                self.next(); // Consume '('
+                let inner = Box::new(self.parse_pattern()?);
+                if self.current_token_type() != &TokenType::RightParen {
+                    return Err("Expected ')' after pattern".into());
+                }
+                match name.as_str() {
+                    "some" => Pattern::Some(inner),
+                    "ok" => Pattern::Ok(inner),
+                    _ => Pattern::Err(inner),
+                }
+            }
+            // Circle(r), Empty
+            TokenType::Identifier(name) if is_constructor(&name) => {
+                let mut args = Vec::new();
+                if self.next_token_type() == &TokenType::LeftParen {
+                    self.next(); // Consume name
+                    self.next(); // Consume '('
+                    while self.current_token_type() != &TokenType::RightParen {
+                        args.push(self.parse_pattern()?);
+                        if self.current_token_type() == &TokenType::Comma {
+                            self.next(); // Consume ','
+                        } else {
+                            break;
+                        }
+                    }
+                    if self.current_token_type() != &TokenType::RightParen {
+                        return Err("Expected ')' after patterns".into());
+                    }
+                }
+                Pattern::Constructor { name, args }
+            }
+            TokenType::Identifier(name) => Pattern::Binding(name),
+            _ => return Err("Expected pattern after '|'".into()),
+        };
+        self.next(); // Consume the last token of the pattern
+
+        Ok(pattern)
+    }
+}
+
+// Reports the first literal the lexer couldn't read, at its position.
+fn check_literals(tokens: &[Token]) -> Result<(), String> {
+    for token in tokens {
+        let problem = match &token.token_type {
+            TokenType::UnterminatedString => "Unterminated string starting".to_string(),
+            TokenType::IntOutOfRange(text) => format!("Integer literal out of range: {}", text),
+            _ => continue,
+        };
+        return Err(format!(
+            "{} at line {}, column {}",
+            problem, token.range.start.line, token.range.start.column
+        ));
+    }
+    Ok(())
+}
+
+// The code of a string placeholder must be a single expression.
+fn parse_placeholder(tokens: Vec<Token>) -> Result<Expr, String> {
+    if tokens.is_empty() {
+        return Err("Expected an expression in string placeholder".into());
+    }
+    check_literals(&tokens)?;
+    let mut parser = Parser::new(tokens);
+    let expr = parser.parse_expr()?;
+    if parser.current_token_type() != &TokenType::EOF {
+        return Err("Expected '}' after string placeholder expression".into());
+    }
+    Ok(expr)
+}
+
+// Constructors of user-defined types are capitalized, which tells a
+// constructor pattern apart from a name binding.
+fn is_constructor(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_uppercase())
 }
@@ -0,0 +1,231 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::{Expr, Pattern, Segment, Stmt, AST};
+
+// Static pass run between parsing and evaluation. It reports undefined
+// variables up front and rewrites identifiers that refer to function
+// parameters into `Expr::Local` so the evaluator can fetch them by index
+// instead of searching every scope by name. Identifiers left untouched are
+// globals (builtins and top-level `let` bindings).
+pub struct Resolver {
+    globals: HashSet<String>,
+    constructors: HashMap<String, usize>,
+    scopes: Vec<Vec<String>>,
+}
+
+impl Resolver {
+    pub fn new(globals: impl IntoIterator<Item = String>) -> Self {
+        Resolver {
+            globals: globals.into_iter().collect(),
+            constructors: HashMap::new(),
+            scopes: Vec::new(),
+        }
+    }
+
+    pub fn resolve(&mut self, ast: &mut AST) -> Result<(), String> {
+        for stmt in ast.stmts.iter_mut() {
+            self.resolve_stmt(stmt)?;
+        }
+        Ok(())
+    }
+
+    fn resolve_stmt(&mut self, stmt: &mut Stmt) -> Result<(), String> {
+        match stmt {
+            Stmt::Expr(expr) | Stmt::Print(expr) => self.resolve_expr(expr),
+            Stmt::Let { name, val } => {
+                // The binding is only visible to the statements that follow.
+                self.resolve_expr(val)?;
+                self.globals.insert(name.clone());
+                Ok(())
+            }
+            Stmt::Type { variants, .. } => {
+                for variant in variants.iter() {
+                    if self.constructors.contains_key(&variant.name) {
+                        return Err(format!("Duplicate constructor: {}", variant.name));
+                    }
+                    for (i, field) in variant.fields.iter().enumerate() {
+                        if variant.fields[..i].contains(field) {
+                            return Err(format!(
+                                "Duplicate field {} in constructor {}",
+                                field, variant.name
+                            ));
+                        }
+                    }
+                    self.constructors
+                        .insert(variant.name.clone(), variant.fields.len());
+                    self.globals.insert(variant.name.clone());
+                }
+                Ok(())
+            }
+        }
+    }
+
+    pub fn resolve_expr(&mut self, expr: &mut Expr) -> Result<(), String> {
+        match expr {
+            Expr::Func { params, body } => {
+                self.scopes.push(params.clone());
+                let result = self.resolve_expr(body);
+                self.scopes.pop();
+                result
+            }
+            Expr::If { cond, then, else_ } => {
+                self.resolve_expr(cond)?;
+                self.resolve_expr(then)?;
+                self.resolve_expr(else_)
+            }
+            Expr::Match { cond, cases } => {
+                self.resolve_expr(cond)?;
+                for case in cases.iter_mut() {
+                    self.check_pattern(&case.pattern)?;
+                    // Mirrors the evaluator, which only creates a frame for
+                    // cases whose pattern binds names.
+                    let bindings = case.pattern.bindings();
+                    if bindings.is_empty() {
+                        self.resolve_expr(&mut case.body)?;
+                    } else {
+                        self.scopes.push(bindings);
+                        let result = self.resolve_expr(&mut case.body);
+                        self.scopes.pop();
+                        result?;
+                    }
+                }
+                Ok(())
+            }
+            Expr::Try { body, fallback } => {
+                self.resolve_expr(body)?;
+                if let Some(fallback) = fallback {
+                    self.resolve_expr(fallback)?;
+                }
+                Ok(())
+            }
+            Expr::List(items) => {
+                for item in items.iter_mut() {
+                    self.resolve_expr(item)?;
+                }
+                Ok(())
+            }
+            Expr::Record(fields) => {
+                for (_, value) in fields.iter_mut() {
+                    self.resolve_expr(value)?;
+                }
+                Ok(())
+            }
+            Expr::Update { record, fields } => {
+                self.resolve_expr(record)?;
+                for (_, value) in fields.iter_mut() {
+                    self.resolve_expr(value)?;
+                }
+                Ok(())
+            }
+            Expr::Pipe { left, right, .. }
+            | Expr::Logic { left, right, .. }
+            | Expr::Comp { left, right, .. }
+            | Expr::Term { left, right, .. }
+            | Expr::Factor { left, right, .. } => {
+                self.resolve_expr(left)?;
+                self.resolve_expr(right)
+            }
+            Expr::Range { start, end } => {
+                self.resolve_expr(start)?;
+                self.resolve_expr(end)
+            }
+            Expr::Unary { right, .. } => self.resolve_expr(right),
+            Expr::Index { list, index } => {
+                self.resolve_expr(list)?;
+                self.resolve_expr(index)
+            }
+            Expr::Slice { list, start, end } => {
+                self.resolve_expr(list)?;
+                if let Some(start) = start {
+                    self.resolve_expr(start)?;
+                }
+                if let Some(end) = end {
+                    self.resolve_expr(end)?;
+                }
+                Ok(())
+            }
+            Expr::Call { name, args, .. } => {
+                self.resolve_expr(name)?;
+                for arg in args.iter_mut() {
+                    self.resolve_expr(arg)?;
+                }
+                Ok(())
+            }
+            Expr::Access { record, .. } => self.resolve_expr(record),
+            Expr::Construct { args, .. } => {
+                for arg in args.iter_mut() {
+                    self.resolve_expr(arg)?;
+                }
+                Ok(())
+            }
+            Expr::Identifier(name) => {
+                if let Some(local) = self.lookup(name) {
+                    *expr = local;
+                    Ok(())
+                } else if self.globals.contains(name) {
+                    Ok(())
+                } else {
+                    Err(format!("Undefined variable: {}", name))
+                }
+            }
+            Expr::Interpolated(segments) => {
+                for segment in segments.iter_mut() {
+                    if let Segment::Expr(expr) = segment {
+                        self.resolve_expr(expr)?;
+                    }
+                }
+                Ok(())
+            }
+            Expr::Local { .. }
+            | Expr::Int(_)
+            | Expr::Number(_)
+            | Expr::Decimal(_)
+            | Expr::String(_)
+            | Expr::Boolean(_)
+            | Expr::Unit
+            | Expr::None => Ok(()),
+        }
+    }
+
+    // Constructor patterns must name a known constructor with the right
+    // number of fields. Constructors from earlier runs (e.g. previous REPL
+    // inputs) are only known as globals, their arity isn't checked.
+    fn check_pattern(&self, pattern: &Pattern) -> Result<(), String> {
+        match pattern {
+            Pattern::Constructor { name, args } => {
+                match self.constructors.get(name) {
+                    Some(arity) if *arity != args.len() => {
+                        return Err(format!(
+                            "Constructor {} has {} fields, but the pattern has {}",
+                            name,
+                            arity,
+                            args.len()
+                        ));
+                    }
+                    None if !self.globals.contains(name) => {
+                        return Err(format!("Undefined constructor: {}", name));
+                    }
+                    _ => {}
+                }
+                args.iter().try_for_each(|arg| self.check_pattern(arg))
+            }
+            Pattern::Some(inner) | Pattern::Ok(inner) | Pattern::Err(inner) => {
+                self.check_pattern(inner)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<Expr> {
+        for (depth, scope) in self.scopes.iter().rev().enumerate() {
+            if let Some(slot) = scope.iter().rposition(|param| param == name) {
+                return Some(Expr::Local {
+                    name: name.to_string(),
+                    depth,
+                    slot,
+                });
+            }
+        }
+        None
+    }
+}
@@ -1,3 +1,5 @@
+use rust_decimal::Decimal;
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Position {
     pub line: usize,
@@ -13,6 +15,7 @@ pub struct Range {
 #[derive(Debug, Clone, PartialEq)]
 pub enum TokenType {
     Let,                // let
+    Type,               // type
     If,                 // if
     Then,               // then
     Else,               // else
@@ -20,6 +23,8 @@ pub enum TokenType {
     Fn,                 // fn
     In,                 // in
     Match,              // match
+    Try,                // try
+    With,               // with
     Pipe,               // |
     Underscore,         // _
     ForwardPipe,        // |>
@@ -36,6 +41,7 @@ pub enum TokenType {
     Not,                // not
     True,               // true
     False,              // false
+    None,               // none
     Comma,              // ,
     Dot,                // .
     DotDot,             // ..
@@ -47,17 +53,61 @@ pub enum TokenType {
     LeftBrace,          // {
     RightBrace,         // }
     Identifier(String), // variable names
-    Number(f64),        // numbers
+    Int(i64),           // integers
+    Number(f64),        // floating point numbers
+    Decimal(Decimal),   // decimals, e.g. 12.30d
     String(String),     // strings
     Plus,               // +
     Minus,              // -
     Asterisk,           // *
     Slash,              // /
+    SlashSlash,         // //
     Percent,            // %
     Invalid,            // invalid token
+    UnterminatedString, // string missing its closing quote
+    // integer literals too large for an int, e.g. 99999999999999999999
+    IntOutOfRange(String),
+    // strings with `{...}` placeholders, e.g. "id: {id}"
+    Interpolated(Vec<StringPart>),
     EOF,
 }
 
+// A piece of an interpolated string literal.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StringPart {
+    Text(String),
+    Code(Vec<Token>), // the tokens of a placeholder, without its braces
+}
+
+impl TokenType {
+    // The word a keyword token is written as, None for other tokens.
+    pub fn keyword(&self) -> Option<&'static str> {
+        let word = match self {
+            TokenType::Let => "let",
+            TokenType::Type => "type",
+            TokenType::If => "if",
+            TokenType::Then => "then",
+            TokenType::Else => "else",
+            TokenType::For => "for",
+            TokenType::Fn => "fn",
+            TokenType::In => "in",
+            TokenType::Match => "match",
+            TokenType::Try => "try",
+            TokenType::With => "with",
+            TokenType::Return => "return",
+            TokenType::And => "and",
+            TokenType::Or => "or",
+            TokenType::Is => "is",
+            TokenType::Not => "not",
+            TokenType::True => "true",
+            TokenType::False => "false",
+            TokenType::None => "none",
+            _ => return None,
+        };
+        Some(word)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Token {
     pub token_type: TokenType,
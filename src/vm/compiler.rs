@@ -0,0 +1,439 @@
+use std::rc::Rc;
+
+use crate::ast::{Expr, Segment, Stmt, AST};
+use crate::eval::value::Value;
+use crate::vm::opcode::{CaptureSource, Chunk, OpCode, Proto};
+
+// Compilation state of a single function body.
+struct FnState {
+    name: String,
+    params: Vec<String>,
+    chunk: Chunk,
+    locals: usize, // slots in use
+    max_locals: usize,
+    captures: Vec<(Captured, CaptureSource)>,
+}
+
+impl FnState {
+    fn new(name: String, params: Vec<String>) -> Self {
+        FnState {
+            name,
+            locals: params.len(),
+            max_locals: params.len(),
+            params,
+            chunk: Chunk::default(),
+            captures: Vec::new(),
+        }
+    }
+
+    fn into_proto(self) -> Rc<Proto> {
+        Rc::new(Proto {
+            name: self.name,
+            params: self.params,
+            locals: self.max_locals,
+            captures: self.captures.into_iter().map(|(_, src)| src).collect(),
+            chunk: self.chunk,
+        })
+    }
+}
+
+enum Var {
+    Local(usize),
+    Capture(usize),
+    Global,
+}
+
+// A variable a function captures, so that every use of it shares one
+// capture.
+#[derive(Clone, PartialEq)]
+enum Captured {
+    Local { level: usize, slot: usize },
+    Global(String),
+}
+
+// A scope of the resolver, the parameters of a function or the names bound
+// by a match case: the function at `level` holds its names from slot `base`.
+struct Scope {
+    level: usize,
+    base: usize,
+}
+
+pub struct Compiler {
+    states: Vec<FnState>,
+    scopes: Vec<Scope>,
+}
+
+// Compiles a whole program into the body of an argument-less script function.
+pub fn compile(ast: &AST) -> Result<Rc<Proto>, String> {
+    let mut compiler = Compiler::new("<script>");
+    for stmt in &ast.stmts {
+        compiler.compile_stmt(stmt)?;
+    }
+    compiler.emit(OpCode::Halt);
+    Ok(compiler.finish())
+}
+
+// Compiles a single expression into a function returning its value.
+pub fn compile_expr(expr: &Expr) -> Result<Rc<Proto>, String> {
+    let mut compiler = Compiler::new("<expr>");
+    compiler.compile_expr(expr)?;
+    compiler.emit(OpCode::Return);
+    Ok(compiler.finish())
+}
+
+impl Compiler {
+    fn new(name: &str) -> Self {
+        Compiler {
+            states: vec![FnState::new(name.to_string(), vec![])],
+            scopes: Vec::new(),
+        }
+    }
+
+    fn finish(mut self) -> Rc<Proto> {
+        let state = self.states.pop().expect("no function being compiled");
+        state.into_proto()
+    }
+
+    /**
+     * Utilities
+     */
+    fn state(&mut self) -> &mut FnState {
+        self.states.last_mut().expect("no function being compiled")
+    }
+    fn chunk(&mut self) -> &mut Chunk {
+        &mut self.state().chunk
+    }
+    fn emit(&mut self, op: OpCode) -> usize {
+        self.chunk().emit(op)
+    }
+    fn here(&mut self) -> usize {
+        self.chunk().code.len()
+    }
+    fn patch(&mut self, at: usize) {
+        let target = self.here();
+        match &mut self.chunk().code[at] {
+            OpCode::Jump(addr) | OpCode::JumpIfFalse(addr) | OpCode::Try(addr) => *addr = target,
+            OpCode::JumpUnlessMatch { target: addr, .. } => *addr = target,
+            op => unreachable!("cannot patch {:?}", op),
+        }
+    }
+    fn add_local(&mut self) -> usize {
+        let state = self.state();
+        state.locals += 1;
+        state.max_locals = state.max_locals.max(state.locals);
+        state.locals - 1
+    }
+    fn pop_local(&mut self) {
+        self.state().locals -= 1;
+    }
+    fn level(&self) -> usize {
+        self.states.len() - 1
+    }
+
+    // Where the function at `level` finds a variable, capturing it from the
+    // enclosing functions when needed. Globals are read by name at the top
+    // level only, functions capture them when they are created like any
+    // other variable, so they keep the value a global had at that point as
+    // they do in the tree walker.
+    fn capture(&mut self, level: usize, var: &Captured) -> Var {
+        match var {
+            Captured::Local { level: owner, slot } if *owner == level => return Var::Local(*slot),
+            Captured::Global(_) if level == 0 => return Var::Global,
+            _ => {}
+        }
+        let state = &self.states[level];
+        if let Some(idx) = state.captures.iter().position(|(c, _)| c == var) {
+            return Var::Capture(idx);
+        }
+        let source = match (self.capture(level - 1, var), var) {
+            (Var::Local(slot), _) => CaptureSource::Local(slot),
+            (Var::Capture(idx), _) => CaptureSource::Capture(idx),
+            (Var::Global, Captured::Global(name)) => {
+                CaptureSource::Global(self.states[level - 1].chunk.name(name))
+            }
+            (Var::Global, Captured::Local { .. }) => unreachable!("locals are never global"),
+        };
+        let captures = &mut self.states[level].captures;
+        captures.push((var.clone(), source));
+        Var::Capture(captures.len() - 1)
+    }
+
+    /**
+     * Compiling methods
+     */
+    fn compile_stmt(&mut self, stmt: &Stmt) -> Result<(), String> {
+        match stmt {
+            Stmt::Expr(expr) => {
+                self.compile_expr(expr)?;
+                self.emit(OpCode::Pop);
+                Ok(())
+            }
+            Stmt::Let { name, val } => {
+                match val {
+                    Expr::Func { params, body } => self.compile_func(name, params, body)?,
+                    _ => self.compile_expr(val)?,
+                }
+                let idx = self.chunk().name(name);
+                self.emit(OpCode::DefineGlobal(idx));
+                Ok(())
+            }
+            Stmt::Type { variants, .. } => {
+                for variant in variants {
+                    if variant.fields.is_empty() {
+                        self.compile_constant(Value::Variant {
+                            tag: variant.name.clone(),
+                            fields: vec![],
+                        });
+                    } else {
+                        let body = variant.constructor();
+                        self.compile_func(&variant.name, &variant.fields, &body)?;
+                    }
+                    let idx = self.chunk().name(&variant.name);
+                    self.emit(OpCode::DefineGlobal(idx));
+                }
+                Ok(())
+            }
+            _ => Err("Unsupported statement type".into()),
+        }
+    }
+
+    fn compile_func(&mut self, name: &str, params: &[String], body: &Expr) -> Result<(), String> {
+        self.states
+            .push(FnState::new(name.to_string(), params.to_vec()));
+        self.scopes.push(Scope {
+            level: self.level(),
+            base: 0,
+        });
+        let result = self.compile_expr(body);
+        self.scopes.pop();
+        result?;
+        self.emit(OpCode::Return);
+        let state = self.states.pop().expect("no function being compiled");
+        self.chunk().protos.push(state.into_proto());
+        let idx = self.chunk().protos.len() - 1;
+        self.emit(OpCode::Closure(idx));
+        Ok(())
+    }
+
+    fn compile_expr(&mut self, expr: &Expr) -> Result<(), String> {
+        match expr {
+            Expr::Func { params, body } => self.compile_func("<lambda>", params, body)?,
+            Expr::If { cond, then, else_ } => {
+                self.compile_expr(cond)?;
+                let to_else = self.emit(OpCode::JumpIfFalse(0));
+                self.compile_expr(then)?;
+                let to_end = self.emit(OpCode::Jump(0));
+                self.patch(to_else);
+                self.compile_expr(else_)?;
+                self.patch(to_end);
+            }
+            Expr::Match { cond, cases } => {
+                self.compile_expr(cond)?;
+                let slot = self.add_local();
+                self.emit(OpCode::SetLocal(slot));
+                let mut to_end = Vec::new();
+                for case in cases {
+                    self.chunk().patterns.push(case.pattern.clone());
+                    let pattern = self.chunk().patterns.len() - 1;
+                    let bindings = case.pattern.bindings().len();
+                    let bind = self.state().locals;
+                    for _ in 0..bindings {
+                        self.add_local();
+                    }
+                    let to_next = self.emit(OpCode::JumpUnlessMatch {
+                        slot,
+                        pattern,
+                        bind,
+                        target: 0,
+                    });
+                    // Like the resolver, only cases binding names have a
+                    // scope of their own.
+                    if bindings > 0 {
+                        self.scopes.push(Scope {
+                            level: self.level(),
+                            base: bind,
+                        });
+                    }
+                    let result = self.compile_expr(&case.body);
+                    if bindings > 0 {
+                        self.scopes.pop();
+                    }
+                    result?;
+                    for _ in 0..bindings {
+                        self.pop_local();
+                    }
+                    to_end.push(self.emit(OpCode::Jump(0)));
+                    self.patch(to_next);
+                }
+                self.emit(OpCode::NoMatch);
+                for at in to_end {
+                    self.patch(at);
+                }
+                self.pop_local();
+            }
+            Expr::Try { body, fallback } => {
+                // The handler starts with the error record on the stack.
+                let handler = self.emit(OpCode::Try(0));
+                self.compile_expr(body)?;
+                self.emit(OpCode::EndTry);
+                if fallback.is_none() {
+                    self.emit(OpCode::WrapResult(true));
+                }
+                let to_end = self.emit(OpCode::Jump(0));
+                self.patch(handler);
+                match fallback {
+                    Some(fallback) => {
+                        self.emit(OpCode::Pop);
+                        self.compile_expr(fallback)?;
+                    }
+                    None => {
+                        self.emit(OpCode::WrapResult(false));
+                    }
+                }
+                self.patch(to_end);
+            }
+            Expr::List(items) => {
+                for item in items {
+                    self.compile_expr(item)?;
+                }
+                self.emit(OpCode::List(items.len()));
+            }
+            Expr::Record(fields) => {
+                let mut names = Vec::new();
+                for (key, value) in fields {
+                    self.compile_expr(value)?;
+                    names.push(self.chunk().name(key));
+                }
+                self.emit(OpCode::Record(names));
+            }
+            Expr::Update { record, fields } => {
+                self.compile_expr(record)?;
+                let mut names = Vec::new();
+                for (key, value) in fields {
+                    self.compile_expr(value)?;
+                    names.push(self.chunk().name(key));
+                }
+                self.emit(OpCode::Update(names));
+            }
+            Expr::Pipe { left, right, span } => {
+                self.compile_expr(left)?;
+                self.compile_expr(right)?;
+                self.chunk().spans.push(span.clone());
+                let span = self.chunk().spans.len() - 1;
+                self.emit(OpCode::Pipe(span));
+            }
+            Expr::Logic { left, op, right } => {
+                self.compile_expr(left)?;
+                self.compile_expr(right)?;
+                self.emit(OpCode::Logic(op.clone()));
+            }
+            Expr::Comp { left, op, right } => {
+                self.compile_expr(left)?;
+                self.compile_expr(right)?;
+                self.emit(OpCode::Comp(op.clone()));
+            }
+            Expr::Range { start, end } => {
+                self.compile_expr(start)?;
+                self.compile_expr(end)?;
+                self.emit(OpCode::Range);
+            }
+            Expr::Term { left, op, right } => {
+                self.compile_expr(left)?;
+                self.compile_expr(right)?;
+                self.emit(OpCode::Term(op.clone()));
+            }
+            Expr::Factor { left, op, right } => {
+                self.compile_expr(left)?;
+                self.compile_expr(right)?;
+                self.emit(OpCode::Factor(op.clone()));
+            }
+            Expr::Unary { op, right } => {
+                self.compile_expr(right)?;
+                self.emit(OpCode::Unary(op.clone()));
+            }
+            Expr::Index { list, index } => {
+                self.compile_expr(list)?;
+                self.compile_expr(index)?;
+                self.emit(OpCode::Index);
+            }
+            Expr::Slice { list, start, end } => {
+                self.compile_expr(list)?;
+                if let Some(start) = start {
+                    self.compile_expr(start)?;
+                }
+                if let Some(end) = end {
+                    self.compile_expr(end)?;
+                }
+                self.emit(OpCode::Slice(start.is_some(), end.is_some()));
+            }
+            Expr::Call { name, args, span } => {
+                self.compile_expr(name)?;
+                for arg in args {
+                    self.compile_expr(arg)?;
+                }
+                self.chunk().spans.push(span.clone());
+                let span = self.chunk().spans.len() - 1;
+                self.emit(OpCode::Call(args.len(), span));
+            }
+            Expr::Access { record, field } => {
+                self.compile_expr(record)?;
+                let idx = self.chunk().name(field);
+                self.emit(OpCode::Access(idx));
+            }
+            Expr::Construct { name, args } => {
+                for arg in args {
+                    self.compile_expr(arg)?;
+                }
+                let idx = self.chunk().name(name);
+                self.emit(OpCode::Construct(idx, args.len()));
+            }
+            // Identifiers the resolver left alone are globals.
+            Expr::Identifier(name) => self.compile_var(&Captured::Global(name.clone())),
+            Expr::Local { name, depth, slot } => {
+                let scope = self
+                    .scopes
+                    .len()
+                    .checked_sub(depth + 1)
+                    .map(|idx| &self.scopes[idx])
+                    .ok_or_else(|| format!("Undefined variable: {}", name))?;
+                let var = Captured::Local {
+                    level: scope.level,
+                    slot: scope.base + slot,
+                };
+                self.compile_var(&var);
+            }
+            Expr::Int(n) => self.compile_constant(Value::Int(*n)),
+            Expr::Number(n) => self.compile_constant(Value::Number(*n)),
+            Expr::Decimal(d) => self.compile_constant(Value::Decimal(*d)),
+            Expr::String(s) => self.compile_constant(Value::String(s.clone())),
+            Expr::Interpolated(segments) => {
+                for segment in segments {
+                    match segment {
+                        Segment::Text(text) => self.compile_constant(Value::String(text.clone())),
+                        Segment::Expr(expr) => self.compile_expr(expr)?,
+                    }
+                }
+                self.emit(OpCode::Interpolate(segments.len()));
+            }
+            Expr::Boolean(b) => self.compile_constant(Value::Boolean(*b)),
+            Expr::Unit => self.compile_constant(Value::Unit),
+            Expr::None => self.compile_constant(Value::Option(None)),
+        }
+        Ok(())
+    }
+
+    fn compile_var(&mut self, var: &Captured) {
+        let op = match (self.capture(self.level(), var), var) {
+            (Var::Local(slot), _) => OpCode::Local(slot),
+            (Var::Capture(idx), _) => OpCode::Capture(idx),
+            (Var::Global, Captured::Global(name)) => OpCode::Global(self.chunk().name(name)),
+            (Var::Global, Captured::Local { .. }) => unreachable!("locals are never global"),
+        };
+        self.emit(op);
+    }
+
+    fn compile_constant(&mut self, value: Value) {
+        let idx = self.chunk().constant(value);
+        self.emit(OpCode::Constant(idx));
+    }
+}
@@ -0,0 +1,3 @@
+pub mod compiler;
+pub mod opcode;
+pub mod vm;
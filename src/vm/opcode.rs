@@ -0,0 +1,95 @@
+use std::rc::Rc;
+
+use crate::ast::{CompOp, FactorOp, LogicOp, Pattern, TermOp, UnaryOp};
+use crate::eval::value::Value;
+use crate::token::Range;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OpCode {
+    Constant(usize),         // push constants[idx]
+    Local(usize),            // push the local in the given slot of the current frame
+    SetLocal(usize),         // pop into the given local slot
+    Capture(usize),          // push a value captured by the current closure
+    Global(usize),           // push the global named names[idx]
+    DefineGlobal(usize),     // pop into the global named names[idx]
+    List(usize),             // pop n values into a list
+    Record(Vec<usize>),      // pop one value per field name in names
+    Update(Vec<usize>),      // pop one value per field name, then the record they replace
+    Closure(usize),          // create a closure from protos[idx]
+    Call(usize, usize),      // call the function below n arguments, located by spans[idx]
+    Pipe(usize),             // call the function on top with the value below it, at spans[idx]
+    Logic(LogicOp),          // and, or
+    Comp(CompOp),            // is, is not, in, <, <=, >, >=
+    Term(TermOp),            // +, -
+    Factor(FactorOp),        // *, /, %
+    Unary(UnaryOp),          // -, not
+    Range,                   // start..end
+    Index,                   // list[index]
+    Slice(bool, bool),       // list[start..end], flags tell which bounds were pushed
+    Access(usize),           // record.names[idx]
+    Construct(usize, usize), // variant tagged names[idx] from the top n values
+    Interpolate(usize),      // join the top n values into a string
+    Jump(usize),             // jump to the absolute address
+    JumpIfFalse(usize),      // pop a boolean and jump if it is false
+    Try(usize),              // install an error handler at the absolute address
+    EndTry,                  // remove the innermost error handler
+    WrapResult(bool),        // wrap the top of the stack in ok (true) or err (false)
+    JumpUnlessMatch {
+        slot: usize,    // local holding the value being matched
+        pattern: usize, // index into patterns
+        bind: usize,    // first local receiving the values bound by the pattern
+        target: usize,  // address to jump to when the pattern does not match
+    },
+    NoMatch, // raise the "no matching case" error
+    Pop,     // discard the top of the stack
+    Return,  // return the top of the stack to the caller
+    Halt,    // stop the script without producing a value
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Chunk {
+    pub code: Vec<OpCode>,
+    pub constants: Vec<Value>,
+    pub names: Vec<String>,
+    pub patterns: Vec<Pattern>,
+    pub spans: Vec<Range>,
+    pub protos: Vec<Rc<Proto>>,
+}
+
+impl Chunk {
+    pub fn emit(&mut self, op: OpCode) -> usize {
+        self.code.push(op);
+        self.code.len() - 1
+    }
+
+    pub fn constant(&mut self, value: Value) -> usize {
+        self.constants.push(value);
+        self.constants.len() - 1
+    }
+
+    pub fn name(&mut self, name: &str) -> usize {
+        if let Some(idx) = self.names.iter().position(|n| n == name) {
+            return idx;
+        }
+        self.names.push(name.to_string());
+        self.names.len() - 1
+    }
+}
+
+// Where a closure gets a captured value from when it is created.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CaptureSource {
+    Local(usize),
+    Capture(usize),
+    Global(usize), // the global named names[idx]
+}
+
+// A compiled function body.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Proto {
+    pub name: String,
+    pub params: Vec<String>,
+    pub locals: usize,
+    pub captures: Vec<CaptureSource>,
+    pub chunk: Chunk,
+}
@@ -0,0 +1,467 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::{Rc, Weak};
+
+use crate::ast::{Expr, AST};
+use crate::eval::builtin::builtin::BUILTIN_FUNCTIONS;
+use crate::eval::context::Io;
+use crate::eval::error::Error;
+use crate::eval::eval::apply;
+use crate::eval::ops;
+use crate::eval::value::Value;
+use crate::token::Range;
+use crate::vm::compiler;
+use crate::vm::opcode::{CaptureSource, OpCode, Proto};
+
+pub type Globals = Rc<RefCell<HashMap<String, Value>>>;
+
+// A function value created by the VM.
+pub struct Closure {
+    pub proto: Rc<Proto>,
+    pub captures: Vec<Value>,
+    pub bound: Vec<Value>, // arguments applied so far (currying)
+    pub globals: Weak<RefCell<HashMap<String, Value>>>,
+}
+
+impl fmt::Debug for Closure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Closure")
+            .field("name", &self.proto.name)
+            .field("params", &self.proto.params)
+            .field("bound", &self.bound)
+            .finish()
+    }
+}
+
+impl PartialEq for Closure {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.proto, &other.proto)
+            && self.captures == other.captures
+            && self.bound == other.bound
+    }
+}
+
+struct Frame {
+    closure: Rc<Closure>,
+    ip: usize,
+    base: usize,
+    span: Option<Range>, // where the closure was called from
+}
+
+// Installed by `try`, restores the frames and stack as they were when the
+// body started and continues at the handler.
+struct Handler {
+    frames: usize,
+    stack: usize,
+    target: usize,
+}
+
+pub struct Vm {
+    globals: Globals,
+    io: Io,
+    stack: Vec<Value>,
+    frames: Vec<Frame>,
+    handlers: Vec<Handler>,
+    depth: usize, // call depth of the bottom frame
+}
+
+impl Default for Vm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Vm {
+    pub fn new() -> Self {
+        Self::with_io(Io::default())
+    }
+
+    // A VM whose builtins use the given streams.
+    pub fn with_io(io: Io) -> Self {
+        let mut globals = HashMap::new();
+        for func in BUILTIN_FUNCTIONS {
+            globals.insert(func.name.to_string(), func.to_value());
+        }
+        Vm {
+            globals: Rc::new(RefCell::new(globals)),
+            io,
+            stack: Vec::new(),
+            frames: Vec::new(),
+            handlers: Vec::new(),
+            depth: 0,
+        }
+    }
+
+    // Defines a global before running anything, e.g. a function of the
+    // application embedding the VM.
+    pub fn define(&mut self, name: &str, value: Value) {
+        self.globals.borrow_mut().insert(name.to_string(), value);
+    }
+
+    pub fn get(&self, name: &str) -> Option<Value> {
+        self.globals.borrow().get(name).cloned()
+    }
+
+    pub fn io(&self) -> &Io {
+        &self.io
+    }
+
+    // Names of the globals defined so far, used to seed the resolver.
+    pub fn names(&self) -> Vec<String> {
+        self.globals.borrow().keys().cloned().collect()
+    }
+
+    // Compiles and runs a program. Top-level `let` bindings stay defined
+    // afterwards, so the same VM can be reused by the REPL.
+    pub fn run(&mut self, ast: &AST) -> Result<(), Error> {
+        let proto = compiler::compile(ast)?;
+        self.execute(proto).map(|_| ())
+    }
+
+    pub fn eval_expr(&mut self, expr: &Expr) -> Result<Value, Error> {
+        let proto = compiler::compile_expr(expr)?;
+        self.execute(proto)
+            .map(|value| value.expect("expression did not produce a value"))
+    }
+
+    fn execute(&mut self, proto: Rc<Proto>) -> Result<Option<Value>, Error> {
+        let closure = Rc::new(Closure {
+            proto,
+            captures: vec![],
+            bound: vec![],
+            globals: Rc::downgrade(&self.globals),
+        });
+        self.stack.clear();
+        self.frames.clear();
+        self.handlers.clear();
+        self.depth = self.io.budget.depth();
+        self.push_frame(closure, vec![], None)?;
+        let result = self.dispatch();
+        self.io.budget.leave(self.depth);
+        result
+    }
+
+    fn push_frame(
+        &mut self,
+        closure: Rc<Closure>,
+        args: Vec<Value>,
+        span: Option<&Range>,
+    ) -> Result<(), Error> {
+        self.io.budget.enter(self.depth + self.frames.len())?;
+        let base = self.stack.len();
+        self.stack.extend(args);
+        // Slots for locals other than the parameters are always written
+        // before being read, any value works as a placeholder.
+        self.stack.resize(base + closure.proto.locals, Value::Unit);
+        self.frames.push(Frame {
+            closure,
+            ip: 0,
+            base,
+            span: span.cloned(),
+        });
+        Ok(())
+    }
+
+    // Brings the call depth of the budget back to the frame now on top.
+    fn leave_frames(&self) {
+        if let Some(top) = self.frames.len().checked_sub(1) {
+            self.io.budget.leave(self.depth + top);
+        }
+    }
+
+    fn global(&self, name: &str) -> Result<Value, Error> {
+        match self.globals.borrow().get(name) {
+            Some(value) => Ok(value.clone()),
+            None => Err(format!("Undefined variable: {}", name).into()),
+        }
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("stack underflow")
+    }
+
+    // Runs until the outermost frame returns. Errors raised inside a `try`
+    // body resume at its handler with the error record on the stack, unless
+    // `try` may not catch them.
+    fn dispatch(&mut self) -> Result<Option<Value>, Error> {
+        loop {
+            match self.run_frames() {
+                Err(error) => {
+                    let handler = match self.handlers.pop() {
+                        Some(handler) if error.is_catchable() => handler,
+                        _ => return Err(self.trace(error)),
+                    };
+                    self.frames.truncate(handler.frames);
+                    self.leave_frames();
+                    self.stack.truncate(handler.stack);
+                    self.stack.push(error.to_value());
+                    self.frames.last_mut().expect("no active frame").ip = handler.target;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    // Adds the calls still on the frame stack to the trace of an error, all
+    // but the outermost frame, which is the code being run rather than a call.
+    fn trace(&self, mut error: Error) -> Error {
+        for frame in self.frames.iter().skip(1).rev() {
+            error = error.in_call(&frame.closure.proto.name, frame.span.as_ref());
+        }
+        error
+    }
+
+    fn run_frames(&mut self) -> Result<Option<Value>, Error> {
+        loop {
+            self.io.budget.step()?;
+            let frame = self.frames.last_mut().expect("no active frame");
+            let closure = Rc::clone(&frame.closure);
+            let base = frame.base;
+            let chunk = &closure.proto.chunk;
+            let op = &chunk.code[frame.ip];
+            frame.ip += 1;
+
+            match op {
+                OpCode::Constant(idx) => self.stack.push(chunk.constants[*idx].clone()),
+                OpCode::Local(slot) => self.stack.push(self.stack[base + slot].clone()),
+                OpCode::SetLocal(slot) => {
+                    let value = self.pop();
+                    self.stack[base + slot] = value;
+                }
+                OpCode::Capture(idx) => self.stack.push(closure.captures[*idx].clone()),
+                OpCode::Global(idx) => {
+                    let value = self.global(&chunk.names[*idx])?;
+                    self.stack.push(value);
+                }
+                OpCode::DefineGlobal(idx) => {
+                    let value = self.pop();
+                    self.globals
+                        .borrow_mut()
+                        .insert(chunk.names[*idx].clone(), value);
+                }
+                OpCode::List(len) => {
+                    self.io.budget.list(*len)?;
+                    let items = self.stack.split_off(self.stack.len() - len);
+                    self.stack.push(Value::List(items.into()));
+                }
+                OpCode::Record(names) => {
+                    self.io.budget.alloc(names.len())?;
+                    let values = self.stack.split_off(self.stack.len() - names.len());
+                    let mut record = im_rc::OrdMap::new();
+                    for (idx, value) in names.iter().zip(values) {
+                        record.insert(chunk.names[*idx].clone(), value);
+                    }
+                    self.stack.push(Value::Record(record));
+                }
+                OpCode::Update(names) => {
+                    let values = self.stack.split_off(self.stack.len() - names.len());
+                    let record = self.pop();
+                    let fields = names
+                        .iter()
+                        .map(|idx| chunk.names[*idx].clone())
+                        .zip(values)
+                        .collect();
+                    self.stack
+                        .push(ops::update(record, fields, &self.io.budget)?);
+                }
+                OpCode::Closure(idx) => {
+                    let proto = Rc::clone(&chunk.protos[*idx]);
+                    let mut captures = Vec::new();
+                    for src in &proto.captures {
+                        captures.push(match src {
+                            CaptureSource::Local(slot) => self.stack[base + slot].clone(),
+                            CaptureSource::Capture(idx) => closure.captures[*idx].clone(),
+                            CaptureSource::Global(idx) => self.global(&chunk.names[*idx])?,
+                        });
+                    }
+                    self.stack.push(Value::Closure(Rc::new(Closure {
+                        proto,
+                        captures,
+                        bound: vec![],
+                        globals: closure.globals.clone(),
+                    })));
+                }
+                OpCode::Call(argc, span) => {
+                    let args = self.stack.split_off(self.stack.len() - argc);
+                    let callee = self.pop();
+                    self.call_value(callee, args, Some(&chunk.spans[*span]))?;
+                }
+                OpCode::Pipe(span) => {
+                    let callee = self.pop();
+                    let left = self.pop();
+                    if !callee.is_function() {
+                        return Err("Pipe requires a function on the right".into());
+                    }
+                    self.call_value(callee, vec![left], Some(&chunk.spans[*span]))?;
+                }
+                OpCode::Logic(op) => {
+                    let right = self.pop();
+                    let left = self.pop();
+                    self.stack.push(ops::logic(op, left, right)?);
+                }
+                OpCode::Comp(op) => {
+                    let right = self.pop();
+                    let left = self.pop();
+                    self.stack.push(ops::comp(op, left, right)?);
+                }
+                OpCode::Term(op) => {
+                    let right = self.pop();
+                    let left = self.pop();
+                    self.stack.push(ops::term(op, left, right)?);
+                }
+                OpCode::Factor(op) => {
+                    let right = self.pop();
+                    let left = self.pop();
+                    self.stack.push(ops::factor(op, left, right)?);
+                }
+                OpCode::Unary(op) => {
+                    let right = self.pop();
+                    self.stack.push(ops::unary(op, right)?);
+                }
+                OpCode::Range => {
+                    let end = self.pop();
+                    let start = self.pop();
+                    self.stack.push(ops::range(start, end, &self.io.budget)?);
+                }
+                OpCode::Index => {
+                    let index = self.pop();
+                    let list = self.pop();
+                    self.stack.push(ops::index(list, index)?);
+                }
+                OpCode::Slice(has_start, has_end) => {
+                    let end = if *has_end { Some(self.pop()) } else { None };
+                    let start = if *has_start { Some(self.pop()) } else { None };
+                    let list = self.pop();
+                    self.stack.push(ops::slice(list, start, end)?);
+                }
+                OpCode::Access(idx) => {
+                    let record = self.pop();
+                    self.stack.push(ops::access(record, &chunk.names[*idx])?);
+                }
+                OpCode::Construct(idx, len) => {
+                    let fields = self.stack.split_off(self.stack.len() - len);
+                    self.stack.push(Value::Variant {
+                        tag: chunk.names[*idx].clone(),
+                        fields,
+                    });
+                }
+                OpCode::Interpolate(len) => {
+                    let values = self.stack.split_off(self.stack.len() - len);
+                    self.stack.push(ops::interpolate(&values));
+                }
+                OpCode::Jump(addr) => {
+                    self.frames.last_mut().expect("no active frame").ip = *addr;
+                }
+                OpCode::JumpIfFalse(addr) => match self.pop() {
+                    Value::Boolean(true) => {}
+                    Value::Boolean(false) => {
+                        self.frames.last_mut().expect("no active frame").ip = *addr;
+                    }
+                    _ => return Err("Condition must be a boolean".into()),
+                },
+                OpCode::Try(addr) => self.handlers.push(Handler {
+                    frames: self.frames.len(),
+                    stack: self.stack.len(),
+                    target: *addr,
+                }),
+                OpCode::EndTry => {
+                    self.handlers.pop();
+                }
+                OpCode::WrapResult(is_ok) => {
+                    let value = Box::new(self.pop());
+                    self.stack
+                        .push(Value::Result(if *is_ok { Ok(value) } else { Err(value) }));
+                }
+                OpCode::JumpUnlessMatch {
+                    slot,
+                    pattern,
+                    bind,
+                    target,
+                } => match ops::destructure(&chunk.patterns[*pattern], &self.stack[base + slot]) {
+                    Some(values) => {
+                        for (i, value) in values.into_iter().enumerate() {
+                            self.stack[base + bind + i] = value;
+                        }
+                    }
+                    None => {
+                        self.frames.last_mut().expect("no active frame").ip = *target;
+                    }
+                },
+                OpCode::NoMatch => return Err("No matching case found".into()),
+                OpCode::Pop => {
+                    self.pop();
+                }
+                OpCode::Return => {
+                    let result = self.pop();
+                    self.frames.pop();
+                    self.leave_frames();
+                    self.stack.truncate(base);
+                    if self.frames.is_empty() {
+                        return Ok(Some(result));
+                    }
+                    self.stack.push(result);
+                }
+                OpCode::Halt => {
+                    self.frames.pop();
+                    self.leave_frames();
+                    self.stack.truncate(base);
+                    return Ok(None);
+                }
+            }
+        }
+    }
+
+    // Calls a function value. Closures get a new frame that the dispatch
+    // loop continues with, every other result is pushed right away. Errors
+    // raised by builtins are located at `span`, the call when there is one.
+    // Calls of closures given all their arguments run in a new frame of
+    // this VM, anything else goes through `eval::apply`.
+    fn call_value(
+        &mut self,
+        callee: Value,
+        args: Vec<Value>,
+        span: Option<&Range>,
+    ) -> Result<(), Error> {
+        match callee {
+            Value::Closure(closure)
+                if closure.bound.len() + args.len() == closure.proto.params.len() =>
+            {
+                let mut all_args = closure.bound.clone();
+                all_args.extend(args);
+                self.push_frame(closure, all_args, span)
+            }
+            callee => {
+                let result = apply(callee, args, &self.io, span)?;
+                self.stack.push(result);
+                Ok(())
+            }
+        }
+    }
+}
+
+// Calls a VM closure from outside the dispatch loop, e.g. from builtins
+// such as `map` that receive a function value as an argument.
+pub fn call(closure: &Rc<Closure>, args: Vec<Value>, io: &Io) -> Result<Value, Error> {
+    let globals = closure
+        .globals
+        .upgrade()
+        .ok_or("Function outlived the program that defined it")?;
+    let mut vm = Vm {
+        globals,
+        io: io.clone(),
+        stack: Vec::new(),
+        frames: Vec::new(),
+        handlers: Vec::new(),
+        depth: io.budget.depth() + 1,
+    };
+    let depth = io.budget.depth();
+    vm.call_value(Value::Closure(Rc::clone(closure)), args, None)?;
+    if vm.frames.is_empty() {
+        // Partially applied, the result is already on the stack.
+        return Ok(vm.pop());
+    }
+    let result = vm.dispatch();
+    io.budget.leave(depth);
+    result.map(|value| value.expect("function did not return a value"))
+}
@@ -3,6 +3,7 @@ use dolang::ast::{CompOp, FactorOp, LogicOp, TermOp, UnaryOp};
 use dolang::eval::env::Env;
 use dolang::eval::eval::eval_expr;
 use dolang::eval::value::Value;
+use dolang::vm::vm::Vm;
 
 #[test]
 fn test_eval_expr() {
@@ -85,5 +86,8 @@ fn test_eval_expr() {
         let mut env = Env::new(None);
         let result = eval_expr(&input, &mut env);
         assert_eq!(result, expected, "Failed for input: {:?}", input);
+
+        let result = Vm::new().eval_expr(&input);
+        assert_eq!(result, expected, "VM failed for input: {:?}", input);
     }
 }
@@ -0,0 +1,510 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::Duration;
+
+use dolang::eval::error::ErrorKind;
+use dolang::eval::limits::{Limits, DEFAULT_DEPTH, SCRIPT_STACK_SIZE};
+use dolang::eval::sandbox::Sandbox;
+use dolang::eval::value::Value;
+use dolang::lsp::lsp::Backend;
+use dolang::Interpreter;
+use rust_decimal::Decimal;
+use tower_lsp::LspService;
+
+// An interpreter with a `scale(factor, x)` native and a `record(value)`
+// native collecting the values it is called with.
+fn interpreter(use_vm: bool) -> (Interpreter, Rc<RefCell<Vec<Value>>>) {
+    let recorded = Rc::new(RefCell::new(Vec::new()));
+    let sink = recorded.clone();
+    let interpreter = Interpreter::builder()
+        .vm(use_vm)
+        .function(
+            "scale",
+            2,
+            "Multiplies a number by a factor.",
+            |ctx, args| match args.as_slice() {
+                [Value::Int(factor), Value::Int(x)] => Ok(Value::Int(factor * x)),
+                _ => Err(ctx.error("scale: expected two integers")),
+            },
+        )
+        .function("record", 1, "Records a value.", move |_, args| {
+            sink.borrow_mut().extend(args);
+            Ok(Value::Unit)
+        })
+        .build();
+    (interpreter, recorded)
+}
+
+#[test]
+fn test_natives() {
+    let test_cases = vec![
+        (
+            "record([1, 2] |> map(scale(10)))",
+            Value::List(im_rc::vector![Value::Int(10), Value::Int(20)]),
+        ),
+        ("let double = scale(2)\nrecord(double(21))", Value::Int(42)),
+        ("record(try scale(1, \"x\") else 0)", Value::Int(0)),
+    ];
+
+    for use_vm in [false, true] {
+        for (input, expected) in &test_cases {
+            let (mut interpreter, recorded) = interpreter(use_vm);
+            assert_eq!(interpreter.run(input), Ok(()), "Failed for: {}", input);
+            assert_eq!(
+                *recorded.borrow(),
+                vec![expected.clone()],
+                "Failed for: {}",
+                input
+            );
+        }
+    }
+}
+
+#[test]
+fn test_interpreter_errors() {
+    let test_cases = vec![
+        ("let = 1", ErrorKind::Syntax),
+        ("record(nope)", ErrorKind::Syntax),
+        ("scale(1, true)", ErrorKind::Builtin),
+        ("record(1 // 0)", ErrorKind::Runtime),
+    ];
+
+    for use_vm in [false, true] {
+        for (input, expected) in &test_cases {
+            let (mut interpreter, _) = interpreter(use_vm);
+            let error = interpreter.run(input).expect_err(input);
+            assert_eq!(error.kind, *expected, "Failed for: {}", input);
+        }
+    }
+}
+
+#[test]
+fn test_natives_are_completed() {
+    let (interpreter, _) = interpreter(false);
+    let (service, _) =
+        LspService::new(|client| Backend::with_natives(client, interpreter.natives()));
+    let items = service.inner().builtin_items.blocking_read();
+    let scale = items
+        .iter()
+        .find(|item| item.label == "scale")
+        .expect("scale is completed");
+    assert_eq!(
+        scale.detail.as_deref(),
+        Some("Multiplies a number by a factor.")
+    );
+}
+
+#[test]
+fn test_eval_str() {
+    let test_cases = vec![
+        ("1 + 2", Value::Int(3)),
+        ("let x = 20\nx + 1", Value::Int(21)),
+        ("let x = 1", Value::Unit),
+        ("[1, 2] |> map(scale(3))", Value::from(vec![3, 6])),
+        ("base * 2", Value::Int(20)),
+        ("greeting", Value::from("hello")),
+    ];
+
+    for use_vm in [false, true] {
+        for (input, expected) in &test_cases {
+            let mut interpreter = Interpreter::builder()
+                .vm(use_vm)
+                .function("scale", 2, "", |_, args| match args.as_slice() {
+                    [Value::Int(factor), Value::Int(x)] => Ok(Value::Int(factor * x)),
+                    _ => Ok(Value::Unit),
+                })
+                .global("base", 10)
+                .global("greeting", "hello")
+                .build();
+            assert_eq!(
+                interpreter.eval_str(input).as_ref(),
+                Ok(expected),
+                "Failed for: {}",
+                input
+            );
+        }
+    }
+}
+
+#[test]
+fn test_calling_dolang_functions() {
+    for use_vm in [false, true] {
+        let mut interpreter = Interpreter::builder().vm(use_vm).build();
+        interpreter
+            .run("let area = fn w, h -> w * h\nlet unit = \"cm\"")
+            .unwrap();
+
+        let area = interpreter.call("area", vec![3.into(), 4.into()]);
+        assert_eq!(area.and_then(i64::try_from), Ok(12));
+
+        // Partial application gives back a function.
+        let by_two = interpreter.call("area", vec![2.into()]).unwrap();
+        assert!(by_two.is_function());
+        assert_eq!(
+            interpreter.apply(&by_two, vec![5.into()]),
+            Ok(Value::Int(10))
+        );
+
+        let inc = interpreter.eval_str("fn x -> x + 1").unwrap();
+        assert_eq!(interpreter.apply(&inc, vec![1.into()]), Ok(Value::Int(2)));
+
+        assert_eq!(
+            interpreter.get_global("unit").map(String::try_from),
+            Some(Ok("cm".to_string()))
+        );
+        interpreter.set_global("unit", "mm");
+        assert_eq!(interpreter.eval_str("unit"), Ok(Value::from("mm")));
+
+        let error = interpreter.call("volume", vec![]).unwrap_err();
+        assert_eq!(error.message, "Undefined variable: volume");
+        let error = interpreter.call("area", vec![1.into(), 2.into(), 3.into()]);
+        assert_eq!(
+            error.unwrap_err().message,
+            "Function area requires 2 arguments, but got 3"
+        );
+    }
+}
+
+#[test]
+fn test_value_conversions() {
+    let values: HashMap<String, Vec<Option<i64>>> =
+        HashMap::from([("xs".to_string(), vec![Some(1), None])]);
+    let value = Value::from(values.clone());
+    assert_eq!(HashMap::try_from(value), Ok(values));
+
+    assert_eq!(f64::try_from(Value::Int(2)), Ok(2.0));
+    assert_eq!(
+        Decimal::try_from(Value::from(Decimal::new(125, 2))),
+        Ok(Decimal::new(125, 2))
+    );
+    assert_eq!(Value::from(Ok::<_, String>(true)), {
+        Value::Result(Ok(Box::new(Value::Boolean(true))))
+    });
+    assert_eq!(<()>::try_from(Value::from(())), Ok(()));
+
+    let error = i64::try_from(Value::from("1")).unwrap_err();
+    assert_eq!(error.message, "Expected int, but got string");
+    let error = Vec::<bool>::try_from(Value::from(vec![true.into(), Value::Unit])).unwrap_err();
+    assert_eq!(error.message, "Expected bool, but got unit");
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde() {
+    let json = r#"{"name":"ada","tags":["a","b"],"age":36,"score":1.5,"nickname":null}"#;
+    let value: Value = serde_json::from_str(json).unwrap();
+
+    let mut interpreter = Interpreter::builder().global("user", value).build();
+    assert_eq!(interpreter.eval_str("user.age + 1"), Ok(Value::Int(37)));
+
+    let value = interpreter
+        .eval_str("{ name: user.name, total: ok(12.30d), tags: user.tags }")
+        .unwrap();
+    assert_eq!(
+        serde_json::to_string(&value).unwrap(),
+        r#"{"name":"ada","tags":["a","b"],"total":{"ok":"12.30"}}"#
+    );
+
+    let func = interpreter.eval_str("fn x -> x").unwrap();
+    assert!(serde_json::to_string(&func).is_err());
+}
+
+#[test]
+fn test_sandbox() {
+    let root = std::env::temp_dir().join(format!("dolang-sandbox-{}", std::process::id()));
+    let data = root.join("data");
+    std::fs::create_dir_all(&data).unwrap();
+    std::fs::write(data.join("a.txt"), "data").unwrap();
+    std::fs::write(root.join("secret.txt"), "secret").unwrap();
+    let path = |name: &str| root.join(name).display().to_string();
+
+    let test_cases = vec![
+        (Sandbox::unrestricted(), "secret.txt", Ok("secret")),
+        (Sandbox::deny_all(), "data/a.txt", Err(())),
+        (
+            Sandbox::deny_all().allow_read(&data),
+            "data/a.txt",
+            Ok("data"),
+        ),
+        (Sandbox::deny_all().allow_read(&data), "secret.txt", Err(())),
+        (
+            Sandbox::deny_all().allow_read(&data),
+            "data/../secret.txt",
+            Err(()),
+        ),
+        (
+            Sandbox::deny_all().allow_read(&root),
+            "data/../secret.txt",
+            Ok("secret"),
+        ),
+    ];
+
+    for use_vm in [false, true] {
+        for (sandbox, file, expected) in &test_cases {
+            let mut interpreter = Interpreter::builder()
+                .vm(use_vm)
+                .sandbox(sandbox.clone())
+                .global("path", path(file))
+                .build();
+            let result = interpreter.eval_str("read_file(path)");
+            match expected {
+                Ok(content) => {
+                    assert_eq!(result, Ok(Value::from(*content)), "Failed for: {}", file)
+                }
+                Err(()) => {
+                    let error = result.expect_err(file);
+                    assert_eq!(error.kind, ErrorKind::Permission, "Failed for: {}", file);
+                    assert_eq!(
+                        error.message,
+                        format!("Permission denied: cannot read {}", path(file))
+                    );
+                    // `try` doesn't catch sandbox denials
+                    let error = interpreter
+                        .eval_str("try read_file(path) else \"\"")
+                        .expect_err(file);
+                    assert_eq!(error.kind, ErrorKind::Permission, "Failed for: {}", file);
+                }
+            }
+        }
+    }
+
+    // Writing files and reading the environment are checked like reading.
+    for use_vm in [false, true] {
+        let mut interpreter = Interpreter::builder()
+            .vm(use_vm)
+            .sandbox(Sandbox::deny_all().allow_write(&data))
+            .global("inside", path("data/b.txt"))
+            .global("outside", path("b.txt"))
+            .build();
+        assert_eq!(
+            interpreter.eval_str("write_file(inside, \"b\")"),
+            Ok(Value::Unit)
+        );
+        assert_eq!(std::fs::read_to_string(data.join("b.txt")).unwrap(), "b");
+        let error = interpreter
+            .eval_str("write_file(outside, \"b\")")
+            .unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Permission);
+        assert_eq!(
+            error.message,
+            format!("Permission denied: cannot write {}", path("b.txt"))
+        );
+        assert!(!root.join("b.txt").exists());
+        let error = interpreter.eval_str("env_var(\"PATH\")").unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Permission);
+        assert_eq!(
+            error.message,
+            "Permission denied: cannot read environment variable PATH"
+        );
+    }
+    let mut interpreter = Interpreter::builder()
+        .sandbox(Sandbox::deny_all().allow_env())
+        .build();
+    assert_eq!(
+        interpreter.eval_str("env_var(\"PATH\")"),
+        Ok(Value::from(std::env::var("PATH").ok()))
+    );
+
+    let sandbox = Sandbox::deny_all().allow_write(&data);
+    assert_eq!(sandbox.check_write(&path("data/b.txt")), Ok(()));
+    assert!(sandbox.check_write(&path("b.txt")).is_err());
+    assert!(sandbox.check_env("HOME").is_err());
+    assert_eq!(sandbox.allow_env().check_env("HOME"), Ok(()));
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_limits() {
+    let recurse = "let f = fn g, n -> if n > 0 then 1 + g(g, n - 1) else 0\n";
+    let test_cases = vec![
+        (
+            Limits {
+                list_len: Some(100),
+                ..Limits::default()
+            },
+            "len(1..1000000000000)".to_string(),
+            "Limit exceeded: list of 1000000000000 elements, more than 100",
+        ),
+        (
+            Limits {
+                list_len: Some(2),
+                ..Limits::default()
+            },
+            "[1, 2, 3]".to_string(),
+            "Limit exceeded: list of 3 elements, more than 2",
+        ),
+        (
+            Limits {
+                list_len: Some(2),
+                ..Limits::default()
+            },
+            "append([1, 2], 3)".to_string(),
+            "Limit exceeded: list of 3 elements, more than 2 (line 1, column 1)",
+        ),
+        (
+            Limits {
+                values: Some(5),
+                ..Limits::default()
+            },
+            "let xs = 1..3\nlet r = { a: xs, b: 1 }\nmap(fn x -> x, xs)".to_string(),
+            "Limit exceeded: more than 5 values allocated (line 3, column 1)",
+        ),
+        (
+            Limits {
+                values: Some(100),
+                ..Limits::default()
+            },
+            "len(repeat(4611686018427387904, \"a\"))".to_string(),
+            "Limit exceeded: string of 4611686018427387904 bytes, more than 1073741824 (line 1, column 5)",
+        ),
+        (
+            Limits::default(),
+            "pad_left(4611686018427387904, \" \", \"a\")".to_string(),
+            "Limit exceeded: string of 4611686018427387904 bytes, more than 1073741824 (line 1, column 1)",
+        ),
+        (
+            Limits {
+                depth: Some(5),
+                ..Limits::default()
+            },
+            format!("{}f(f, 6)", recurse),
+            "Limit exceeded: calls nested more than 5 deep",
+        ),
+        (
+            Limits {
+                steps: Some(50),
+                ..Limits::default()
+            },
+            format!("{}f(f, 100)", recurse),
+            "Limit exceeded: more than 50 steps",
+        ),
+        // `try` doesn't catch exceeded limits
+        (
+            Limits {
+                steps: Some(50),
+                ..Limits::default()
+            },
+            format!("{}try f(f, 100) else 0", recurse),
+            "Limit exceeded: more than 50 steps",
+        ),
+        (
+            Limits {
+                depth: Some(5),
+                ..Limits::default()
+            },
+            format!("{}try f(f, 6)", recurse),
+            "Limit exceeded: calls nested more than 5 deep",
+        ),
+        (
+            Limits {
+                timeout: Some(Duration::ZERO),
+                ..Limits::default()
+            },
+            "map(fn x -> x + 1, 1..5000)".to_string(),
+            "Limit exceeded: timed out after 0ns",
+        ),
+    ];
+
+    for use_vm in [false, true] {
+        for (limits, input, expected) in &test_cases {
+            let mut interpreter = Interpreter::builder()
+                .vm(use_vm)
+                .limits(limits.clone())
+                .build();
+            let error = interpreter.eval_str(input).expect_err(input);
+            assert_eq!(error.kind, ErrorKind::Limit, "Failed for: {}", input);
+            assert_eq!(error.to_string(), *expected, "Failed for: {}", input);
+        }
+    }
+
+    // Lists built from existing ones are only charged for the elements
+    // they add.
+    for use_vm in [false, true] {
+        let mut interpreter = Interpreter::builder()
+            .vm(use_vm)
+            .limits(Limits {
+                values: Some(250),
+                ..Limits::default()
+            })
+            .build();
+        let input = "let xs = fold(fn acc, x -> append(acc, x), [], 1..100)\nlen(xs |> take(50) |> drop(10))";
+        assert_eq!(interpreter.eval_str(input), Ok(Value::Int(40)));
+    }
+
+    // Every run starts with the full limits, and runs within them pass.
+    for use_vm in [false, true] {
+        let mut interpreter = Interpreter::builder()
+            .vm(use_vm)
+            .limits(Limits {
+                steps: Some(200),
+                depth: Some(5),
+                ..Limits::default()
+            })
+            .build();
+        interpreter.run(recurse).unwrap();
+        for _ in 0..3 {
+            assert_eq!(interpreter.eval_str("f(f, 4)"), Ok(Value::Int(4)));
+        }
+        let error = interpreter.eval_str("try f(f, 6) else -1").unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Limit);
+        assert_eq!(interpreter.eval_str("f(f, 4)"), Ok(Value::Int(4)));
+    }
+}
+
+#[test]
+fn test_default_depth_limit() {
+    // Scripts run on a stack of this size in the CLI.
+    let thread = std::thread::Builder::new()
+        .stack_size(SCRIPT_STACK_SIZE)
+        .spawn(|| {
+            for use_vm in [false, true] {
+                let mut interpreter = Interpreter::builder().vm(use_vm).build();
+                let error = interpreter
+                    .eval_str(
+                        "let f = fn self, n -> if n is 0 then 0 else self(self, n - 1)\nf(f, 10000000)",
+                    )
+                    .unwrap_err();
+                assert_eq!(error.kind, ErrorKind::Limit);
+                assert_eq!(
+                    error.message,
+                    format!("Limit exceeded: calls nested more than {} deep", DEFAULT_DEPTH)
+                );
+            }
+        })
+        .unwrap();
+    thread.join().unwrap();
+}
+
+#[test]
+fn test_pipeline_stages_in_backtraces() {
+    for use_vm in [false, true] {
+        let mut interpreter = Interpreter::builder().vm(use_vm).build();
+        let error = interpreter
+            .eval_str("[1, 0]\n  |> map(fn x -> 10 // x)\n  |> sum")
+            .unwrap_err();
+        assert_eq!(error.message, "Division by zero");
+        assert_eq!(
+            error.backtrace(),
+            "  in <lambda> (line 2, column 6)\n  in map (line 2, column 6)"
+        );
+    }
+}
+
+#[test]
+fn test_repeated_calls_are_collapsed_in_backtraces() {
+    let mut interpreter = Interpreter::builder()
+        .limits(Limits {
+            depth: Some(4),
+            ..Limits::default()
+        })
+        .build();
+    let error = interpreter
+        .eval_str("let f = fn g, n -> g(g, n)\nf(f, 1)")
+        .unwrap_err();
+    assert_eq!(
+        error.backtrace(),
+        "  in f (line 1, column 20)\n  ... repeated 3 more times\n  in f (line 2, column 1)"
+    );
+}
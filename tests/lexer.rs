@@ -1,5 +1,6 @@
 use dolang::lexer::Lexer;
-use dolang::token::{Position, Range, Token, TokenType};
+use dolang::token::{Position, Range, StringPart, Token, TokenType};
+use rust_decimal::Decimal;
 
 #[test]
 fn test_lexer() {
@@ -98,7 +99,7 @@ let odds = nums |> filter(fn n -> n % 2 is not 0)",
                     },
                 },
                 Token {
-                    token_type: TokenType::Number(1.0),
+                    token_type: TokenType::Int(1),
                     range: Range {
                         start: Position { line: 1, column: 13 },
                         end: Position { line: 1, column: 13 },
@@ -112,7 +113,7 @@ let odds = nums |> filter(fn n -> n % 2 is not 0)",
                     },
                 },
                 Token {
-                    token_type: TokenType::Number(2.0),
+                    token_type: TokenType::Int(2),
                     range: Range {
                         start: Position { line: 1, column: 16 },
                         end: Position { line: 1, column: 16 },
@@ -126,7 +127,7 @@ let odds = nums |> filter(fn n -> n % 2 is not 0)",
                     },
                 },
                 Token {
-                    token_type: TokenType::Number(3.0),
+                    token_type: TokenType::Int(3),
                     range: Range {
                         start: Position { line: 1, column: 19 },
                         end: Position { line: 1, column: 19 },
@@ -140,7 +141,7 @@ let odds = nums |> filter(fn n -> n % 2 is not 0)",
                     },
                 },
                 Token {
-                    token_type: TokenType::Number(4.0),
+                    token_type: TokenType::Int(4),
                     range: Range {
                         start: Position { line: 1, column: 22 },
                         end: Position { line: 1, column: 22 },
@@ -154,7 +155,7 @@ let odds = nums |> filter(fn n -> n % 2 is not 0)",
                     },
                 },
                 Token {
-                    token_type: TokenType::Number(5.0),
+                    token_type: TokenType::Int(5),
                     range: Range {
                         start: Position { line: 1, column: 25 },
                         end: Position { line: 1, column: 25 },
@@ -252,7 +253,7 @@ let odds = nums |> filter(fn n -> n % 2 is not 0)",
                     },
                 },
                 Token {
-                    token_type: TokenType::Number(2.0),
+                    token_type: TokenType::Int(2),
                     range: Range {
                         start: Position { line: 2, column: 39 },
                         end: Position { line: 2, column: 39 },
@@ -273,7 +274,7 @@ let odds = nums |> filter(fn n -> n % 2 is not 0)",
                     },
                 },
                 Token {
-                    token_type: TokenType::Number(0.0),
+                    token_type: TokenType::Int(0),
                     range: Range {
                         start: Position { line: 2, column: 48 },
                         end: Position { line: 2, column: 48 },
@@ -287,6 +288,187 @@ let odds = nums |> filter(fn n -> n % 2 is not 0)",
                     },
                 },
             ]
+        ), (
+            "1.5 // -2 99999999999999999999",
+            vec![
+                Token {
+                    token_type: TokenType::Number(1.5),
+                    range: Range {
+                        start: Position { line: 1, column: 1 },
+                        end: Position { line: 1, column: 3 },
+                    },
+                },
+                Token {
+                    token_type: TokenType::SlashSlash,
+                    range: Range {
+                        start: Position { line: 1, column: 5 },
+                        end: Position { line: 1, column: 6 },
+                    },
+                },
+                Token {
+                    token_type: TokenType::Int(-2),
+                    range: Range {
+                        start: Position { line: 1, column: 8 },
+                        end: Position { line: 1, column: 9 },
+                    },
+                },
+                Token {
+                    token_type: TokenType::IntOutOfRange("99999999999999999999".to_string()),
+                    range: Range {
+                        start: Position { line: 1, column: 11 },
+                        end: Position { line: 1, column: 30 },
+                    },
+                },
+            ]
+        ), (
+            "12.30d -5d 1dx",
+            vec![
+                Token {
+                    token_type: TokenType::Decimal(Decimal::new(1230, 2)),
+                    range: Range {
+                        start: Position { line: 1, column: 1 },
+                        end: Position { line: 1, column: 6 },
+                    },
+                },
+                Token {
+                    token_type: TokenType::Decimal(Decimal::new(-5, 0)),
+                    range: Range {
+                        start: Position { line: 1, column: 8 },
+                        end: Position { line: 1, column: 10 },
+                    },
+                },
+                Token {
+                    token_type: TokenType::Int(1),
+                    range: Range {
+                        start: Position { line: 1, column: 12 },
+                        end: Position { line: 1, column: 12 },
+                    },
+                },
+                Token {
+                    token_type: TokenType::Identifier("dx".to_string()),
+                    range: Range {
+                        start: Position { line: 1, column: 13 },
+                        end: Position { line: 1, column: 14 },
+                    },
+                },
+            ]
+        ), (
+            "\"a {x}b\" \"{\"}\"}\"",
+            vec![
+                Token {
+                    token_type: TokenType::Interpolated(vec![
+                        StringPart::Text("a ".to_string()),
+                        StringPart::Code(vec![Token {
+                            token_type: TokenType::Identifier("x".to_string()),
+                            range: Range {
+                                start: Position { line: 1, column: 5 },
+                                end: Position { line: 1, column: 5 },
+                            },
+                        }]),
+                        StringPart::Text("b".to_string()),
+                    ]),
+                    range: Range {
+                        start: Position { line: 1, column: 1 },
+                        end: Position { line: 1, column: 8 },
+                    },
+                },
+                // a closing brace in a string inside a placeholder
+                Token {
+                    token_type: TokenType::Interpolated(vec![StringPart::Code(vec![Token {
+                        token_type: TokenType::String("}".to_string()),
+                        range: Range {
+                            start: Position { line: 1, column: 12 },
+                            end: Position { line: 1, column: 14 },
+                        },
+                    }])]),
+                    range: Range {
+                        start: Position { line: 1, column: 10 },
+                        end: Position { line: 1, column: 16 },
+                    },
+                },
+            ]
+        ), (
+            "\"{x\"",
+            vec![Token {
+                token_type: TokenType::UnterminatedString,
+                range: Range {
+                    start: Position { line: 1, column: 1 },
+                    end: Position { line: 1, column: 4 },
+                },
+            }]
+        ), (
+            "\"\\t\\u{e9}\\\\\" r\"\\d{2}\"",
+            vec![
+                Token {
+                    token_type: TokenType::String("\t\u{e9}\\".to_string()),
+                    range: Range {
+                        start: Position { line: 1, column: 1 },
+                        end: Position { line: 1, column: 12 },
+                    },
+                },
+                // raw strings keep backslashes and braces
+                Token {
+                    token_type: TokenType::String("\\d{2}".to_string()),
+                    range: Range {
+                        start: Position { line: 1, column: 14 },
+                        end: Position { line: 1, column: 21 },
+                    },
+                },
+            ]
+        ), (
+            "\"\"\"\n    a\n      {x}\n\n    \"\"\" \"\"\"say \"hi\"\"\"\"",
+            vec![
+                Token {
+                    token_type: TokenType::Interpolated(vec![
+                        StringPart::Text("a\n  ".to_string()),
+                        StringPart::Code(vec![Token {
+                            token_type: TokenType::Identifier("x".to_string()),
+                            range: Range {
+                                start: Position { line: 3, column: 8 },
+                                end: Position { line: 3, column: 8 },
+                            },
+                        }]),
+                        StringPart::Text("\n".to_string()),
+                    ]),
+                    range: Range {
+                        start: Position { line: 1, column: 1 },
+                        end: Position { line: 5, column: 7 },
+                    },
+                },
+                // quotes before the closing ones are part of the text
+                Token {
+                    token_type: TokenType::String("say \"hi\"".to_string()),
+                    range: Range {
+                        start: Position { line: 5, column: 9 },
+                        end: Position { line: 5, column: 22 },
+                    },
+                },
+            ]
+        ), (
+            "\"\\q\" x \"abc",
+            vec![
+                Token {
+                    token_type: TokenType::Invalid,
+                    range: Range {
+                        start: Position { line: 1, column: 1 },
+                        end: Position { line: 1, column: 4 },
+                    },
+                },
+                Token {
+                    token_type: TokenType::Identifier("x".to_string()),
+                    range: Range {
+                        start: Position { line: 1, column: 6 },
+                        end: Position { line: 1, column: 6 },
+                    },
+                },
+                Token {
+                    token_type: TokenType::UnterminatedString,
+                    range: Range {
+                        start: Position { line: 1, column: 8 },
+                        end: Position { line: 1, column: 11 },
+                    },
+                },
+            ]
         )
     ];
 
@@ -1,7 +1,23 @@
-use dolang::ast::{Case, CompOp, Expr, FactorOp, LogicOp, Pattern, Stmt, TermOp, UnaryOp, AST};
+use dolang::ast::{
+    Case, CompOp, Expr, FactorOp, LogicOp, Pattern, Segment, Stmt, TermOp, UnaryOp, Variant, AST,
+};
 use dolang::lexer::Lexer;
 use dolang::parser::Parser;
-use dolang::token::TokenType;
+use dolang::token::{Position, Range, TokenType};
+
+// A range on the first line, columns are inclusive.
+fn span(start: usize, end: usize) -> Range {
+    Range {
+        start: Position {
+            line: 1,
+            column: start,
+        },
+        end: Position {
+            line: 1,
+            column: end,
+        },
+    }
+}
 
 #[test]
 fn test_parser() {
@@ -9,7 +25,7 @@ fn test_parser() {
         (
             "1",
             Ok(AST {
-                stmts: vec![Stmt::Expr(Expr::Number(1.0))],
+                stmts: vec![Stmt::Expr(Expr::Int(1))],
             }),
         ),
         (
@@ -25,12 +41,12 @@ fn test_parser() {
             "1 + 2 * 3",
             Ok(AST {
                 stmts: vec![Stmt::Expr(Expr::Term {
-                    left: Box::new(Expr::Number(1.0)),
+                    left: Box::new(Expr::Int(1)),
                     op: TermOp::Plus,
                     right: Box::new(Expr::Factor {
-                        left: Box::new(Expr::Number(2.0)),
+                        left: Box::new(Expr::Int(2)),
                         op: FactorOp::Multiply,
-                        right: Box::new(Expr::Number(3.0)),
+                        right: Box::new(Expr::Int(3)),
                     }),
                 })],
             }),
@@ -39,9 +55,9 @@ fn test_parser() {
             "1 + 2",
             Ok(AST {
                 stmts: vec![Stmt::Expr(Expr::Term {
-                    left: Box::new(Expr::Number(1.0)),
+                    left: Box::new(Expr::Int(1)),
                     op: TermOp::Plus,
-                    right: Box::new(Expr::Number(2.0)),
+                    right: Box::new(Expr::Int(2)),
                 })],
             }),
         ),
@@ -50,8 +66,8 @@ fn test_parser() {
             Ok(AST {
                 stmts: vec![Stmt::Expr(Expr::If {
                     cond: Box::new(Expr::Boolean(true)),
-                    then: Box::new(Expr::Number(1.0)),
-                    else_: Box::new(Expr::Number(2.0)),
+                    then: Box::new(Expr::Int(1)),
+                    else_: Box::new(Expr::Int(2)),
                 })],
             }),
         ),
@@ -64,23 +80,110 @@ fn test_parser() {
                     cond: Box::new(Expr::Identifier("x".to_string())),
                     cases: vec![
                         Case {
-                            pattern: Pattern::Number(1.0),
+                            pattern: Pattern::Int(1),
                             body: Expr::String("one".to_string()),
                         },
                         Case {
-                            pattern: Pattern::Number(2.0),
+                            pattern: Pattern::Int(2),
                             body: Expr::String("two".to_string()),
                         },
                     ],
                 })],
             }),
         ),
+        (
+            "try f(x) else 0",
+            Ok(AST {
+                stmts: vec![Stmt::Expr(Expr::Try {
+                    body: Box::new(Expr::Call {
+                        name: Box::new(Expr::Identifier("f".to_string())),
+                        args: vec![Expr::Identifier("x".to_string())],
+                        span: span(5, 8),
+                    }),
+                    fallback: Some(Box::new(Expr::Int(0))),
+                })],
+            }),
+        ),
+        (
+            "match r | ok(some(x)) -> x | err(_) -> () | none -> none",
+            Ok(AST {
+                stmts: vec![Stmt::Expr(Expr::Match {
+                    cond: Box::new(Expr::Identifier("r".to_string())),
+                    cases: vec![
+                        Case {
+                            pattern: Pattern::Ok(Box::new(Pattern::Some(Box::new(
+                                Pattern::Binding("x".to_string()),
+                            )))),
+                            body: Expr::Identifier("x".to_string()),
+                        },
+                        Case {
+                            pattern: Pattern::Err(Box::new(Pattern::Wildcard)),
+                            body: Expr::Unit,
+                        },
+                        Case {
+                            pattern: Pattern::None,
+                            body: Expr::None,
+                        },
+                    ],
+                })],
+            }),
+        ),
+        (
+            "type Shape = Circle(r) | Rect(w, h) | Empty\nmatch s | Rect(w, _) -> w | Empty -> 0",
+            Ok(AST {
+                stmts: vec![
+                    Stmt::Type {
+                        name: "Shape".to_string(),
+                        variants: vec![
+                            Variant {
+                                name: "Circle".to_string(),
+                                fields: vec!["r".to_string()],
+                            },
+                            Variant {
+                                name: "Rect".to_string(),
+                                fields: vec!["w".to_string(), "h".to_string()],
+                            },
+                            Variant {
+                                name: "Empty".to_string(),
+                                fields: vec![],
+                            },
+                        ],
+                    },
+                    Stmt::Expr(Expr::Match {
+                        cond: Box::new(Expr::Identifier("s".to_string())),
+                        cases: vec![
+                            Case {
+                                pattern: Pattern::Constructor {
+                                    name: "Rect".to_string(),
+                                    args: vec![
+                                        Pattern::Binding("w".to_string()),
+                                        Pattern::Wildcard,
+                                    ],
+                                },
+                                body: Expr::Identifier("w".to_string()),
+                            },
+                            Case {
+                                pattern: Pattern::Constructor {
+                                    name: "Empty".to_string(),
+                                    args: vec![],
+                                },
+                                body: Expr::Int(0),
+                            },
+                        ],
+                    }),
+                ],
+            }),
+        ),
+        (
+            "type shape = circle(r)",
+            Err("Error parsing statement: Expected a capitalized constructor name".to_string()),
+        ),
         (
             "let x = 10",
             Ok(AST {
                 stmts: vec![Stmt::Let {
                     name: "x".to_string(),
-                    val: Expr::Number(10.0),
+                    val: Expr::Int(10),
                 }],
             }),
         ),
@@ -104,11 +207,7 @@ fn test_parser() {
             "[1, 2, 3] |> filter(fn x -> x > 1)",
             Ok(AST {
                 stmts: vec![Stmt::Expr(Expr::Pipe {
-                    left: Box::new(Expr::List(vec![
-                        Expr::Number(1.0),
-                        Expr::Number(2.0),
-                        Expr::Number(3.0),
-                    ])),
+                    left: Box::new(Expr::List(vec![Expr::Int(1), Expr::Int(2), Expr::Int(3)])),
                     right: Box::new(Expr::Call {
                         name: Box::new(Expr::Identifier("filter".to_string())),
                         args: vec![Expr::Func {
@@ -116,10 +215,12 @@ fn test_parser() {
                             body: Box::new(Expr::Comp {
                                 left: Box::new(Expr::Identifier("x".to_string())),
                                 op: CompOp::GreaterThan,
-                                right: Box::new(Expr::Number(1.0)),
+                                right: Box::new(Expr::Int(1)),
                             }),
                         }],
+                        span: span(14, 34),
                     }),
+                    span: span(14, 34),
                 })],
             }),
         ),
@@ -138,19 +239,105 @@ fn test_parser() {
             Ok(AST {
                 stmts: vec![Stmt::Expr(Expr::Logic {
                     left: Box::new(Expr::Comp {
-                        left: Box::new(Expr::Number(1.0)),
+                        left: Box::new(Expr::Int(1)),
                         op: CompOp::LessThan,
-                        right: Box::new(Expr::Number(2.0)),
+                        right: Box::new(Expr::Int(2)),
                     }),
                     op: LogicOp::Or,
                     right: Box::new(Expr::Comp {
-                        left: Box::new(Expr::Number(3.0)),
+                        left: Box::new(Expr::Int(3)),
                         op: CompOp::GreaterThan,
-                        right: Box::new(Expr::Number(4.0)),
+                        right: Box::new(Expr::Int(4)),
+                    }),
+                })],
+            }),
+        ),
+        (
+            "\"x = {x + 1}!\"",
+            Ok(AST {
+                stmts: vec![Stmt::Expr(Expr::Interpolated(vec![
+                    Segment::Text("x = ".to_string()),
+                    Segment::Expr(Expr::Term {
+                        left: Box::new(Expr::Identifier("x".to_string())),
+                        op: TermOp::Plus,
+                        right: Box::new(Expr::Int(1)),
                     }),
+                    Segment::Text("!".to_string()),
+                ]))],
+            }),
+        ),
+        (
+            "\"{}\"",
+            Err(
+                "Error parsing statement: Expected an expression in string placeholder".to_string(),
+            ),
+        ),
+        (
+            "\"{x y}\"",
+            Err(
+                "Error parsing statement: Expected '}' after string placeholder expression"
+                    .to_string(),
+            ),
+        ),
+        (
+            "let x = 1\nlet q = \"\"\"\n  abc",
+            Err("Unterminated string starting at line 2, column 9".to_string()),
+        ),
+        (
+            "let x = 99999999999999999999",
+            Err("Integer literal out of range: 99999999999999999999 at line 1, column 9".to_string()),
+        ),
+        (
+            "\"{1 + 9223372036854775808}\"",
+            Err(
+                "Error parsing statement: Integer literal out of range: 9223372036854775808 at line 1, column 7"
+                    .to_string(),
+            ),
+        ),
+        // keywords can name fields
+        (
+            "{ type: \"a\", none: 1 }",
+            Ok(AST {
+                stmts: vec![Stmt::Expr(Expr::Record(vec![
+                    ("type".to_string(), Expr::String("a".to_string())),
+                    ("none".to_string(), Expr::Int(1)),
+                ]))],
+            }),
+        ),
+        (
+            "row.type",
+            Ok(AST {
+                stmts: vec![Stmt::Expr(Expr::Access {
+                    record: Box::new(Expr::Identifier("row".to_string())),
+                    field: "type".to_string(),
                 })],
             }),
         ),
+        (
+            "{ row with with: 1, try: 2 }",
+            Ok(AST {
+                stmts: vec![Stmt::Expr(Expr::Update {
+                    record: Box::new(Expr::Identifier("row".to_string())),
+                    fields: vec![
+                        ("with".to_string(), Expr::Int(1)),
+                        ("try".to_string(), Expr::Int(2)),
+                    ],
+                })],
+            }),
+        ),
+        (
+            "{ user with age: 31 }",
+            Ok(AST {
+                stmts: vec![Stmt::Expr(Expr::Update {
+                    record: Box::new(Expr::Identifier("user".to_string())),
+                    fields: vec![("age".to_string(), Expr::Int(31))],
+                })],
+            }),
+        ),
+        (
+            "{ user with }",
+            Err("Error parsing statement: Expected fields to update after 'with'".to_string()),
+        ),
     ];
 
     for (input, expected) in test_cases {
@@ -0,0 +1,172 @@
+use dolang::ast::{Case, Expr, Pattern, Stmt, TermOp, AST};
+use dolang::lexer::Lexer;
+use dolang::parser::Parser;
+use dolang::resolver::Resolver;
+use dolang::token::{Position, Range, TokenType};
+
+// A range on the first line, columns are inclusive.
+fn span(start: usize, end: usize) -> Range {
+    Range {
+        start: Position {
+            line: 1,
+            column: start,
+        },
+        end: Position {
+            line: 1,
+            column: end,
+        },
+    }
+}
+
+fn resolve(input: &str) -> Result<AST, String> {
+    let mut lexer = Lexer::new(input);
+    let mut tokens = Vec::new();
+    loop {
+        let token = lexer.next_token();
+        if token.token_type == TokenType::EOF {
+            break;
+        }
+        tokens.push(token);
+    }
+    let mut ast = Parser::new(tokens).parse()?;
+    Resolver::new(vec!["map".to_string()]).resolve(&mut ast)?;
+    Ok(ast)
+}
+
+fn local(name: &str, depth: usize, slot: usize) -> Box<Expr> {
+    Box::new(Expr::Local {
+        name: name.to_string(),
+        depth,
+        slot,
+    })
+}
+
+#[test]
+fn test_resolver() {
+    let test_cases = vec![
+        (
+            "let add = fn x, y -> x + y",
+            Ok(AST {
+                stmts: vec![Stmt::Let {
+                    name: "add".to_string(),
+                    val: Expr::Func {
+                        params: vec!["x".to_string(), "y".to_string()],
+                        body: Box::new(Expr::Term {
+                            left: local("x", 0, 0),
+                            op: TermOp::Plus,
+                            right: local("y", 0, 1),
+                        }),
+                    },
+                }],
+            }),
+        ),
+        (
+            "let n = 1\nfn x -> fn y -> x + n",
+            Ok(AST {
+                stmts: vec![
+                    Stmt::Let {
+                        name: "n".to_string(),
+                        val: Expr::Int(1),
+                    },
+                    Stmt::Expr(Expr::Func {
+                        params: vec!["x".to_string()],
+                        body: Box::new(Expr::Func {
+                            params: vec!["y".to_string()],
+                            body: Box::new(Expr::Term {
+                                left: local("x", 1, 0),
+                                op: TermOp::Plus,
+                                right: Box::new(Expr::Identifier("n".to_string())),
+                            }),
+                        }),
+                    }),
+                ],
+            }),
+        ),
+        (
+            // inner parameters shadow outer ones
+            "fn x -> fn x -> x",
+            Ok(AST {
+                stmts: vec![Stmt::Expr(Expr::Func {
+                    params: vec!["x".to_string()],
+                    body: Box::new(Expr::Func {
+                        params: vec!["x".to_string()],
+                        body: local("x", 0, 0),
+                    }),
+                })],
+            }),
+        ),
+        (
+            "map(fn x -> x)",
+            Ok(AST {
+                stmts: vec![Stmt::Expr(Expr::Call {
+                    name: Box::new(Expr::Identifier("map".to_string())),
+                    args: vec![Expr::Func {
+                        params: vec!["x".to_string()],
+                        body: local("x", 0, 0),
+                    }],
+                    span: span(1, 14),
+                })],
+            }),
+        ),
+        (
+            // names bound by a pattern get a scope of their own
+            "fn o -> match o | some(x) -> x | _ -> o",
+            Ok(AST {
+                stmts: vec![Stmt::Expr(Expr::Func {
+                    params: vec!["o".to_string()],
+                    body: Box::new(Expr::Match {
+                        cond: local("o", 0, 0),
+                        cases: vec![
+                            Case {
+                                pattern: Pattern::Some(Box::new(Pattern::Binding("x".to_string()))),
+                                body: *local("x", 0, 0),
+                            },
+                            Case {
+                                pattern: Pattern::Wildcard,
+                                body: *local("o", 0, 0),
+                            },
+                        ],
+                    }),
+                })],
+            }),
+        ),
+        ("x", Err("Undefined variable: x".to_string())),
+        (
+            "type T = A(x) | A(y)",
+            Err("Duplicate constructor: A".to_string()),
+        ),
+        (
+            "type T = P(x, x)",
+            Err("Duplicate field x in constructor P".to_string()),
+        ),
+        (
+            "type T = P(x, y)\nmatch 1 | P(a) -> a | _ -> 0",
+            Err("Constructor P has 2 fields, but the pattern has 1".to_string()),
+        ),
+        (
+            "match 1 | Q -> 1 | _ -> 0",
+            Err("Undefined constructor: Q".to_string()),
+        ),
+        (
+            "match 1 | some(x) -> x | _ -> x",
+            Err("Undefined variable: x".to_string()),
+        ),
+        ("fn x -> y", Err("Undefined variable: y".to_string())),
+        // a binding is not visible in its own definition
+        (
+            "let f = fn x -> f(x)",
+            Err("Undefined variable: f".to_string()),
+        ),
+        // nor before it is defined
+        ("y\nlet y = 1", Err("Undefined variable: y".to_string())),
+    ];
+
+    for (input, expected) in test_cases {
+        assert_eq!(
+            resolve(input),
+            expected,
+            "Failed to resolve input: {}",
+            input
+        );
+    }
+}
@@ -0,0 +1,872 @@
+use std::cell::RefCell;
+use std::io::{Cursor, Write};
+use std::rc::Rc;
+use std::str::FromStr;
+
+use dolang::ast::{Stmt, AST};
+use dolang::eval::context::Io;
+use dolang::eval::env::Env;
+use dolang::eval::error::{Error, ErrorKind};
+use dolang::eval::eval::{eval, eval_expr};
+use dolang::eval::limits::Budget;
+use dolang::eval::sandbox::Sandbox;
+use dolang::eval::value::Value;
+use dolang::lexer::Lexer;
+use dolang::parser::Parser;
+use dolang::resolver::Resolver;
+use dolang::token::TokenType;
+use dolang::vm::vm::Vm;
+use rust_decimal::Decimal;
+
+fn dec(s: &str) -> Decimal {
+    Decimal::from_str(s).unwrap()
+}
+
+fn parse(input: &str) -> AST {
+    let mut lexer = Lexer::new(input);
+    let mut tokens = Vec::new();
+    loop {
+        let token = lexer.next_token();
+        if token.token_type == TokenType::EOF {
+            break;
+        }
+        tokens.push(token);
+    }
+    Parser::new(tokens).parse().expect("failed to parse")
+}
+
+// Runs every statement but the last one, then evaluates the last one as an
+// expression on both the tree-walking evaluator and the VM.
+fn run_both(input: &str) -> (Result<Value, String>, Result<Value, String>) {
+    let (tree, vm) = run_both_with_errors(input);
+    (tree.map_err(|e| e.message), vm.map_err(|e| e.message))
+}
+
+fn run_both_with_errors(input: &str) -> (Result<Value, Error>, Result<Value, Error>) {
+    let mut ast = parse(input);
+    let mut env = Env::new(None);
+    Resolver::new(env.names())
+        .resolve(&mut ast)
+        .expect("failed to resolve");
+    let last = match ast.stmts.pop() {
+        Some(Stmt::Expr(expr)) => expr,
+        _ => panic!("program must end with an expression"),
+    };
+
+    let tree = eval(ast.clone(), &mut env).and_then(|_| eval_expr(&last, &mut env));
+
+    let mut vm = Vm::new();
+    let vm_result = vm.run(&ast).and_then(|_| vm.eval_expr(&last));
+
+    (tree, vm_result)
+}
+
+#[test]
+fn test_vm_matches_tree_walker() {
+    let test_cases = vec![
+        ("1 + 2 * 3", Value::Int(7)),
+        ("let x = 10\nx * 2", Value::Int(20)),
+        ("let add = fn x, y -> x + y\nadd(1, 2)", Value::Int(3)),
+        (
+            "let add = fn x, y -> x + y\nlet inc = add(1)\ninc(41)",
+            Value::Int(42),
+        ),
+        (
+            "let adder = fn x -> fn y -> x + y\nlet add5 = adder(5)\nadd5(10)",
+            Value::Int(15),
+        ),
+        (
+            "let outer = fn a -> fn b -> fn c -> (a + b) + c\nlet f = outer(1)\nlet g = f(2)\ng(3)",
+            Value::Int(6),
+        ),
+        (
+            "[1, 2, 3, 4] |> filter(fn x -> x % 2 is 0) |> map(fn x -> x * 10)",
+            Value::List(im_rc::vector![Value::Int(20), Value::Int(40)]),
+        ),
+        (
+            "let n = 3\nlet scale = fn x -> x * n\n1..3 |> map(scale) |> sum",
+            Value::Int(18),
+        ),
+        (
+            "let name = fn n -> match n | 1 -> \"one\" | 2 -> \"two\" | _ -> \"many\"\n[1, 2, 3] |> map(name)",
+            Value::List(im_rc::vector![
+                Value::String("one".to_string()),
+                Value::String("two".to_string()),
+                Value::String("many".to_string()),
+            ]),
+        ),
+        (
+            "let f = fn x -> if x > 2 then \"big\" else \"small\"\n[f(1), f(5)]",
+            Value::List(im_rc::vector![
+                Value::String("small".to_string()),
+                Value::String("big".to_string()),
+            ]),
+        ),
+        (
+            "let user = { name: \"Mike\", age: 30 }\nuser.age + 1",
+            Value::Int(31),
+        ),
+        ("let xs = [1, 2, 3, 4]\nxs[1..3]", Value::List(im_rc::vector![
+            Value::Int(2),
+            Value::Int(3),
+        ])),
+        ("let s = \"Hello\"\ns[1]", Value::String("e".to_string())),
+        // strings are indexed by characters, not bytes
+        (
+            "let s = \"日本語のCSV\"\nlet idx = [0, 4]\n[len(s), s[1], s[1..3], s[4..], s[idx]]",
+            Value::List(im_rc::vector![
+                Value::Int(7),
+                Value::String("本".to_string()),
+                Value::String("本語".to_string()),
+                Value::String("CSV".to_string()),
+                Value::String("日C".to_string()),
+            ]),
+        ),
+        ("\"語の\" in \"日本語のCSV\"", Value::Boolean(true)),
+        (
+            "graphemes(\"e\\u{301}t\\u{e9}\") |> len",
+            Value::Int(3),
+        ),
+        // calls, pipes and builtins apply functions alike: missing arguments
+        // curry, extra ones go to the result
+        ("let add = fn a -> (fn b -> a + b)\nadd(1, 2)", Value::Int(3)),
+        (
+            "let add3 = fn a, b, c -> (a + b) + c\nlet add1 = add3(1)\n[add1(2, 3), 5 |> add3(1, 2), fold(add3(0), 0, [1, 2])]",
+            Value::List(im_rc::vector![Value::Int(6), Value::Int(8), Value::Int(3)]),
+        ),
+        (
+            "[1, 2] |> map(fn a, b -> a * b) |> map(fn f -> f(10))",
+            Value::List(im_rc::vector![Value::Int(10), Value::Int(20)]),
+        ),
+        // list library, taking lambdas and curried builtins alike
+        (
+            "let xs = [3, 1, 4, 1, 5]\n[reduce(fn a, b -> if a > b then a else b, xs), fold(fn acc, x -> acc + x, 0, xs), count(fn x -> x is 1, xs)]",
+            Value::List(im_rc::vector![Value::Int(5), Value::Int(14), Value::Int(2)]),
+        ),
+        (
+            "let xs = [3, 1, 4, 1, 5]\n[sort(xs), sort_by(fn x -> 0 - x, xs), reverse(xs), unique(xs)]",
+            Value::List(im_rc::vector![Value::List(im_rc::vector![Value::Int(1), Value::Int(1), Value::Int(3), Value::Int(4), Value::Int(5)]), Value::List(im_rc::vector![Value::Int(5), Value::Int(4), Value::Int(3), Value::Int(1), Value::Int(1)]), Value::List(im_rc::vector![Value::Int(5), Value::Int(1), Value::Int(4), Value::Int(1), Value::Int(3)]), Value::List(im_rc::vector![Value::Int(3), Value::Int(1), Value::Int(4), Value::Int(5)])]),
+        ),
+        (
+            "fold(append, [], [[1], [2, 3]]) |> flat_map(fn x -> [x, x * 10])",
+            Value::List(im_rc::vector![Value::Int(1), Value::Int(10), Value::Int(2), Value::Int(20), Value::Int(3), Value::Int(30)]),
+        ),
+        (
+            "let xs = [1, 2, 3, 4, 5]\n[take(2, xs), drop(4, xs), take(9, [1]), chunk(2, xs)]",
+            Value::List(im_rc::vector![Value::List(im_rc::vector![Value::Int(1), Value::Int(2)]), Value::List(im_rc::vector![Value::Int(5)]), Value::List(im_rc::vector![Value::Int(1)]), Value::List(im_rc::vector![Value::List(im_rc::vector![Value::Int(1), Value::Int(2)]), Value::List(im_rc::vector![Value::Int(3), Value::Int(4)]), Value::List(im_rc::vector![Value::Int(5)])])]),
+        ),
+        (
+            "let xs = [1, 2, 3]\n[any(fn x -> x > 2, xs), all(fn x -> x > 2, xs), find(fn x -> x > 1, xs), find(fn x -> x > 5, xs)]",
+            Value::List(im_rc::vector![
+                Value::Boolean(true),
+                Value::Boolean(false),
+                Value::Option(Some(Box::new(Value::Int(2)))),
+                Value::Option(None),
+            ]),
+        ),
+        (
+            "[zip([1, 2, 3], [4, 5]), enumerate([7]), partition(fn x -> x > 1, [1, 2, 3])]",
+            Value::List(im_rc::vector![
+                Value::List(im_rc::vector![Value::List(im_rc::vector![Value::Int(1), Value::Int(4)]), Value::List(im_rc::vector![Value::Int(2), Value::Int(5)])]),
+                Value::List(im_rc::vector![Value::List(im_rc::vector![Value::Int(0), Value::Int(7)])]),
+                Value::List(im_rc::vector![Value::List(im_rc::vector![Value::Int(2), Value::Int(3)]), Value::List(im_rc::vector![Value::Int(1)])]),
+            ]),
+        ),
+        (
+            "group_by(fn s -> len(s), [\"a\", \"bc\", \"d\"])",
+            Value::Record(im_rc::ordmap! {
+                "1".to_string() => Value::List(im_rc::vector![
+                    Value::String("a".to_string()),
+                    Value::String("d".to_string()),
+                ]),
+                "2".to_string() => Value::List(im_rc::vector![Value::String("bc".to_string())])
+            }),
+        ),
+        (
+            "[min([2, 0.5, 3]), max([\"b\", \"c\", \"a\"])]",
+            Value::List(im_rc::vector![
+                Value::Number(0.5),
+                Value::String("c".to_string()),
+            ]),
+        ),
+        // regular expressions, compiled or given as pattern strings
+        (
+            "let num = regex(r\"\\d+\")\n[matches(num, \"a1\"), matches(\"^b\", \"ab\"), find_all(num, \"1 22 x\")]",
+            Value::List(im_rc::vector![
+                Value::Boolean(true),
+                Value::Boolean(false),
+                Value::List(im_rc::vector![
+                    Value::String("1".to_string()),
+                    Value::String("22".to_string()),
+                ]),
+            ]),
+        ),
+        (
+            "[\"id=42\", \"id\"] |> map(captures(r\"(?P<key>\\w+)=(?P<value>\\d+)(?P<unit>s)?\"))",
+            Value::List(im_rc::vector![
+                Value::Option(Some(Box::new(Value::Record(im_rc::ordmap! {
+                    "key".to_string() => Value::String("id".to_string()),
+                    "value".to_string() => Value::String("42".to_string()),
+                    "unit".to_string() => Value::String("".to_string())
+                })))),
+                Value::Option(None),
+            ]),
+        ),
+        (
+            "replace_all(r\"(\\d+)-(?P<b>\\d+)\", \"$b-$1\", \"1-2 30-40\")",
+            Value::String("2-1 40-30".to_string()),
+        ),
+        // string library
+        (
+            "[trim(\" a \"), lower(\"AbC\"), upper(\"straße\"), replace(\"-\", \"/\", \"1-2-3\")]",
+            Value::List(im_rc::vector![
+                Value::String("a".to_string()),
+                Value::String("abc".to_string()),
+                Value::String("STRASSE".to_string()),
+                Value::String("1/2/3".to_string()),
+            ]),
+        ),
+        (
+            "[starts_with(\"ab\", \"abc\"), ends_with(\"ab\", \"abc\"), find(\"本\", \"日本\"), find(\"x\", \"\")]",
+            Value::List(im_rc::vector![
+                Value::Boolean(true),
+                Value::Boolean(false),
+                Value::Option(Some(Box::new(Value::Int(1)))),
+                Value::Option(None),
+            ]),
+        ),
+        (
+            "[\"a\", \"bc\"] |> map(pad_left(3, \".\")) |> join(\", \")",
+            Value::String("..a, .bc".to_string()),
+        ),
+        (
+            "[pad_right(4, \"-\", \"日本\"), repeat(2, \"ab\"), join(\"\", [1, 2.5, true])]",
+            Value::List(im_rc::vector![
+                Value::String("日本--".to_string()),
+                Value::String("abab".to_string()),
+                Value::String("12.5true".to_string()),
+            ]),
+        ),
+        (
+            "[lines(\"a\\r\\nb\\n\"), chars(\"日本\")]",
+            Value::List(im_rc::vector![
+                Value::List(im_rc::vector![
+                    Value::String("a".to_string()),
+                    Value::String("b".to_string()),
+                ]),
+                Value::List(im_rc::vector![
+                    Value::String("日".to_string()),
+                    Value::String("本".to_string()),
+                ]),
+            ]),
+        ),
+        (
+            "let user = { name: \"Ada\", age: 36 }\n[format(r\"{} {1} {0}\", [1, 2]), user |> format(r\"{{{name}}} {age}\")]",
+            Value::List(im_rc::vector![
+                Value::String("1 2 1".to_string()),
+                Value::String("{Ada} 36".to_string()),
+            ]),
+        ),
+        ("\"a,b,c\" |> split(\",\") |> len", Value::Int(3)),
+        ("\"b\" in [\"a\", \"b\"]", Value::Boolean(true)),
+        // integers and floats
+        ("7 / 2", Value::Number(3.5)),
+        ("7 // 2", Value::Int(3)),
+        ("-7 // 2", Value::Int(-4)),
+        ("-7 % 3", Value::Int(2)),
+        (
+            "[-7 % 2, 7 % -2, -6 % 3, -7.0 % 2.0, 7.5 % -2, -7.5 % -2.0]",
+            Value::List(im_rc::vector![
+                Value::Int(1),
+                Value::Int(-1),
+                Value::Int(0),
+                Value::Number(1.0),
+                Value::Number(-0.5),
+                Value::Number(-1.5),
+            ]),
+        ),
+        ("1 + 0.5", Value::Number(1.5)),
+        ("2 is 2.0", Value::Boolean(true)),
+        ("1 < 1.5", Value::Boolean(true)),
+        ("2.0 in [1, 2]", Value::Boolean(true)),
+        ("match 2.0 | 1 -> 1 | 2 -> 2 | _ -> 0", Value::Int(2)),
+        ("sum([1, 2, 3])", Value::Int(6)),
+        ("sum([1, 2.5])", Value::Number(3.5)),
+        ("[int(\"42\"), int(-3.7), int(9)]", Value::List(im_rc::vector![
+            Value::Int(42),
+            Value::Int(-3),
+            Value::Int(9),
+        ])),
+        ("[float(3), float(\"0.25\")]", Value::List(im_rc::vector![
+            Value::Number(3.0),
+            Value::Number(0.25),
+        ])),
+        // decimals
+        ("0.1d + 0.2d", Value::Decimal(dec("0.3"))),
+        ("(0.1d + 0.2d) is 0.3d", Value::Boolean(true)),
+        ("19.99d * 3", Value::Decimal(dec("59.97"))),
+        ("10d / 4", Value::Decimal(dec("2.5"))),
+        ("7.5d // 2", Value::Decimal(dec("3"))),
+        (
+            "let a = decimal(\"-7\")\nlet b = decimal(\"2\")\n[a // b, a % b, ((a // b) * b) + (a % b), 7.5d % -2, -6d % 3]",
+            Value::List(im_rc::vector![
+                Value::Decimal(dec("-4")),
+                Value::Decimal(dec("1")),
+                Value::Decimal(dec("-7")),
+                Value::Decimal(dec("-0.5")),
+                Value::Decimal(dec("0")),
+            ]),
+        ),
+        ("1.10d < 2", Value::Boolean(true)),
+        ("-1.5d", Value::Decimal(dec("-1.5"))),
+        ("sum([1.10d, 2.20d, 3])", Value::Decimal(dec("6.30"))),
+        ("decimal(\"12.30\")", Value::Decimal(dec("12.30"))),
+        ("decimal(0.1)", Value::Decimal(dec("0.1"))),
+        ("str(12.30d)", Value::String("12.30".to_string())),
+        ("[float(1.5d), float(2)]", Value::List(im_rc::vector![
+            Value::Number(1.5),
+            Value::Number(2.0),
+        ])),
+        ("round(2, 2.675d)", Value::Decimal(dec("2.68"))),
+        ("round(2, 2.675)", Value::Number(2.68)),
+        ("[round_with(\"half_even\", 2, 2.665d), round_with(\"down\", 0, -1.5d)]", Value::List(im_rc::vector![
+            Value::Decimal(dec("2.66")),
+            Value::Decimal(dec("-1")),
+        ])),
+        ("1.21d |> round_with(\"ceil\", 1)", Value::Decimal(dec("1.3"))),
+        ("match 2.50d | 2.5d -> \"yes\" | _ -> \"no\"", Value::String("yes".to_string())),
+        // unit, options and results
+        ("println(\"\")", Value::Unit),
+        ("()", Value::Unit),
+        ("first_opt([])", Value::Option(None)),
+        (
+            "first_opt([1, 2])",
+            Value::Option(Some(Box::new(Value::Int(1)))),
+        ),
+        (
+            "let xs = [1, 2, 3]\n[get(1, xs), get(3, xs), get(-1, xs)]",
+            Value::List(im_rc::vector![
+                Value::Option(Some(Box::new(Value::Int(2)))),
+                Value::Option(None),
+                Value::Option(None),
+            ]),
+        ),
+        (
+            "{name: \"Mike\"} |> get(\"age\")",
+            Value::Option(None),
+        ),
+        (
+            "let user = {name: \"Ada\", age: 36}\n[keys(user), values(user), entries(user)]",
+            Value::List(im_rc::vector![
+                Value::List(im_rc::vector![
+                    Value::String("age".to_string()),
+                    Value::String("name".to_string()),
+                ]),
+                Value::List(im_rc::vector![Value::Int(36), Value::String("Ada".to_string())]),
+                Value::List(im_rc::vector![
+                    Value::List(im_rc::vector![Value::String("age".to_string()), Value::Int(36)]),
+                    Value::List(im_rc::vector![
+                        Value::String("name".to_string()),
+                        Value::String("Ada".to_string()),
+                    ]),
+                ]),
+            ]),
+        ),
+        (
+            "from_entries([[\"a\", 1], [\"b\", 2], [\"a\", 3]])",
+            Value::Record(im_rc::ordmap! {
+                "a".to_string() => Value::Int(3),
+                "b".to_string() => Value::Int(2)
+            }),
+        ),
+        (
+            "let r = {a: 1}\n[has(\"a\", r), has(\"b\", r), get_or(\"b\", 0, r), get_or(1, 0, [5, 6])]",
+            Value::List(im_rc::vector![
+                Value::Boolean(true),
+                Value::Boolean(false),
+                Value::Int(0),
+                Value::Int(6),
+            ]),
+        ),
+        (
+            "{a: 1, b: 2} |> set(\"c\", 3) |> remove(\"a\") |> merge({b: 20})",
+            Value::Record(im_rc::ordmap! {
+                "b".to_string() => Value::Int(20),
+                "c".to_string() => Value::Int(3)
+            }),
+        ),
+        (
+            "map_values(fn x -> x * 2, {a: 1, b: 2})",
+            Value::Record(im_rc::ordmap! {
+                "a".to_string() => Value::Int(2),
+                "b".to_string() => Value::Int(4)
+            }),
+        ),
+        (
+            "let user = {name: \"Ada\", age: 36}\nlet older = { user with age: user.age + 1 }\n[user.age, older.age, older.name]",
+            Value::List(im_rc::vector![
+                Value::Int(36),
+                Value::Int(37),
+                Value::String("Ada".to_string()),
+            ]),
+        ),
+        ("parse_int(\" 42 \")", Value::Result(Ok(Box::new(Value::Int(42))))),
+        (
+            "parse_int(\"4x\")",
+            Value::Result(Err(Box::new(Value::String(
+                "invalid integer: 4x".to_string(),
+            )))),
+        ),
+        (
+            "let unwrap = fn o -> match o | some(x) -> x | none -> 0\n[unwrap(some(5)), unwrap(none)]",
+            Value::List(im_rc::vector![Value::Int(5), Value::Int(0)]),
+        ),
+        (
+            "let f = fn d -> match parse_int(\"x\") | ok(n) -> n | err(e) -> d\nf(7)",
+            Value::Int(7),
+        ),
+        (
+            // closures capture names bound by patterns
+            "let adder = match ok(some(3)) | ok(some(x)) -> fn y -> x + y | _ -> fn y -> y\nadder(4)",
+            Value::Int(7),
+        ),
+        // functions see globals as they were when the function was created
+        ("let x = 1\nlet f = fn y -> x\nlet x = 2\nf(0)", Value::Int(1)),
+        (
+            "let x = 1\nlet f = fn a -> (fn b -> (x + a) + b)\nlet g = f(10)\nlet x = 100\n[g(1), f(20, 2)]",
+            Value::List(im_rc::vector![Value::Int(12), Value::Int(23)]),
+        ),
+        (
+            "let f = fn x -> match some(x + 1) | some(x) -> (fn y -> x * y) | none -> (fn y -> y)\nf(1, 5)",
+            Value::Int(10),
+        ),
+        ("some(1) is some(1.0)", Value::Boolean(true)),
+        ("ok(1) is err(1)", Value::Boolean(false)),
+        // user-defined types
+        (
+            "type Shape = Circle(r) | Rect(w, h) | Empty\nlet area = fn s -> match s | Circle(r) -> (3 * r) * r | Rect(w, h) -> w * h | Empty -> 0\n[Circle(2), Rect(2, 5), Empty] |> map(area)",
+            Value::List(im_rc::vector![Value::Int(12), Value::Int(10), Value::Int(0)]),
+        ),
+        (
+            // constructors are curried
+            "type Pair = Pair(a, b)\nlet with_one = Pair(1)\n[2, 3] |> map(with_one)",
+            Value::List(im_rc::vector![
+                Value::Variant {
+                    tag: "Pair".to_string(),
+                    fields: vec![Value::Int(1), Value::Int(2)],
+                },
+                Value::Variant {
+                    tag: "Pair".to_string(),
+                    fields: vec![Value::Int(1), Value::Int(3)],
+                },
+            ]),
+        ),
+        (
+            "type Tree = Leaf | Node(l, v, r)\nmatch Node(Leaf, 1, Node(Leaf, 2, Leaf)) | Node(_, _, Node(_, v, _)) -> v | _ -> 0",
+            Value::Int(2),
+        ),
+        (
+            "type T = A(x) | B(x)\n[A(1) is A(1), A(1) is B(1), A(1) is A(2)]",
+            Value::List(im_rc::vector![
+                Value::Boolean(true),
+                Value::Boolean(false),
+                Value::Boolean(false),
+            ]),
+        ),
+        // try
+        ("try int(\"abc\") else 0", Value::Int(0)),
+        ("try 1 + 1", Value::Result(Ok(Box::new(Value::Int(2))))),
+        (
+            "match try 1 // 0 | ok(n) -> str(n) | err(e) -> e.message",
+            Value::String("Division by zero".to_string()),
+        ),
+        (
+            "let kind = fn r -> match r | err(e) -> e.kind | ok(_) -> \"\"\n[try int(\"x\"), try 1 // 0] |> map(kind)",
+            Value::List(im_rc::vector![
+                Value::String("builtin".to_string()),
+                Value::String("runtime".to_string()),
+            ]),
+        ),
+        (
+            // errors unwind the calls made inside the body
+            "let f = fn x -> 10 // x\nlet g = fn x -> (try f(x) else -1) + 1\n[g(2), g(0)]",
+            Value::List(im_rc::vector![Value::Int(6), Value::Int(0)]),
+        ),
+        ("try map(fn x -> 1 // x, [1, 0]) else 5", Value::Int(5)),
+        // builtins apply any function value, curried ones included
+        (
+            "[1.25, 2.35] |> map(round(1))",
+            Value::List(im_rc::vector![Value::Number(1.3), Value::Number(2.4)]),
+        ),
+        (
+            "let pairs = map(append([0]), [1, 2])\npairs |> map(sum)",
+            Value::List(im_rc::vector![Value::Int(1), Value::Int(2)]),
+        ),
+        // fail and assert
+        ("assert(1 < 2, \"math\")", Value::Unit),
+        ("try assert(1 > 2, \"nope\") else \"caught\"", Value::String("caught".to_string())),
+        (
+            "match try fail(\"boom\") | err(e) -> e.kind | ok(_) -> \"\"",
+            Value::String("user".to_string()),
+        ),
+        ("[str(3), str(3.0)]", Value::List(im_rc::vector![
+            Value::String("3".to_string()),
+            Value::String("3.0".to_string()),
+        ])),
+        (
+            // appending shares structure with, and leaves alone, the original
+            "let xs = 1..100000\nlet ys = append(xs, 0)\n[len(xs), len(ys), ys[100000]]",
+            Value::List(im_rc::vector![
+                Value::Int(100_000),
+                Value::Int(100_001),
+                Value::Int(0),
+            ]),
+        ),
+        // string interpolation formats values like print
+        (
+            "let user = { name: \"Ada\", age: 36 }\n\"{user.name} is {user.age + 1}\"",
+            Value::String("Ada is 37".to_string()),
+        ),
+        (
+            "let xs = [1.0, 2.5]\n\"{xs} {some(1)} {none} {()} {true}\"",
+            Value::String("[1.0, 2.5] some(1) none () true".to_string()),
+        ),
+        (
+            "let f = fn x -> \"<{x}>\"\n\"{map(f, split(\",\", \"a,b\"))}\"",
+            Value::String("[<a>, <b>]".to_string()),
+        ),
+        (
+            "let r = {zeta: 1, alpha: {c: 3, b: 2}, mid: 0}\n\"{r}\"",
+            Value::String("{alpha: {b: 2, c: 3}, mid: 0, zeta: 1}".to_string()),
+        ),
+        (
+            "let r = { {b: 1, a: 2} with b: 3 }\n[keys(r), keys(set(\"0\", 1, r))]",
+            Value::List(im_rc::vector![
+                Value::List(im_rc::vector![
+                    Value::String("a".to_string()),
+                    Value::String("b".to_string()),
+                ]),
+                Value::List(im_rc::vector![
+                    Value::String("0".to_string()),
+                    Value::String("a".to_string()),
+                    Value::String("b".to_string()),
+                ]),
+            ]),
+        ),
+        (
+            "[[1, [2]] is [1, [2]], [1, 2] is [1, 2, 3], {a: [1], b: none} is {b: none, a: [1]}, {a: 1} is not {a: 1.0}, [1] is not [\"1\"]]",
+            Value::List(im_rc::vector![
+                Value::Boolean(true),
+                Value::Boolean(false),
+                Value::Boolean(true),
+                Value::Boolean(false),
+                Value::Boolean(true),
+            ]),
+        ),
+        (
+            "[\"b\" > \"a\", [1, 2] < [1, 3], [1] < [1, 0], [2] >= [1, 9], [1, 2] in [[1], [1, 2]], {a: 1} in [{a: 1}]]",
+            Value::List(im_rc::vector![
+                Value::Boolean(true),
+                Value::Boolean(true),
+                Value::Boolean(true),
+                Value::Boolean(true),
+                Value::Boolean(true),
+                Value::Boolean(true),
+            ]),
+        ),
+        (
+            "[sort([[2], [1, 5], [1]]), unique([[1], {a: 1}, [1], {a: 1}])]",
+            Value::List(im_rc::vector![
+                Value::List(im_rc::vector![
+                    Value::List(im_rc::vector![Value::Int(1)]),
+                    Value::List(im_rc::vector![Value::Int(1), Value::Int(5)]),
+                    Value::List(im_rc::vector![Value::Int(2)]),
+                ]),
+                Value::List(im_rc::vector![
+                    Value::List(im_rc::vector![Value::Int(1)]),
+                    Value::Record(im_rc::ordmap! { "a".to_string() => Value::Int(1) }),
+                ]),
+            ]),
+        ),
+        (
+            "let row = { type: \"a\", if: 1 }\nlet next = { row with type: \"b\" }\n[row.type, next.type, next.if]",
+            Value::List(im_rc::vector![
+                Value::String("a".to_string()),
+                Value::String("b".to_string()),
+                Value::Int(1),
+            ]),
+        ),
+        (
+            "\"\\{ {{ a: 1 }} \\}\"",
+            Value::String("{ {a: 1} }".to_string()),
+        ),
+    ];
+
+    for (input, expected) in test_cases {
+        let (tree, vm) = run_both(input);
+        assert_eq!(
+            tree,
+            Ok(expected.clone()),
+            "Tree walker failed for: {}",
+            input
+        );
+        assert_eq!(vm, Ok(expected), "VM failed for: {}", input);
+    }
+}
+
+#[test]
+fn test_vm_errors() {
+    let test_cases = vec![
+        ("if 1 then 2 else 3", "Condition must be a boolean"),
+        ("match 3 | 1 -> 1 | 2 -> 2", "No matching case found"),
+        ("1 / 0", "Division by zero"),
+        ("1 // 0", "Division by zero"),
+        ("5 % 0", "Division by zero"),
+        ("9223372036854775807 + 1", "Integer overflow"),
+        ("-9223372036854775807 - 2", "Integer overflow"),
+        ("4611686018427387904 * 2", "Integer overflow"),
+        (
+            "1.5 // 2",
+            "Integer division requires integer or decimal operands",
+        ),
+        ("let xs = [1, 2]\nxs[1.0]", "Index must be an integer"),
+        ("let xs = [1, 2]\nxs[-1]", "Index out of bounds: -1"),
+        ("let s = \"日本\"\ns[2]", "Index out of bounds: 2"),
+        (
+            "let s = \"日本\"\ns[1..3]",
+            "Slice indices out of bounds: 1..3",
+        ),
+        ("int(\"1.5\")", "int: invalid integer: 1.5"),
+        (
+            "1.5d + 1.5",
+            "Cannot mix decimal and float operands, convert one with decimal() or float()",
+        ),
+        ("1.5d / 0", "Division by zero"),
+        ("decimal(\"abc\")", "decimal: invalid decimal: abc"),
+        (
+            "round_with(\"sideways\", 1, 1.5d)",
+            "round_with: unknown rounding mode: sideways",
+        ),
+        ("repeat(-1, \"a\")", "repeat: negative count: -1"),
+        (
+            "pad_left(3, \"ab\", \"x\")",
+            "pad_left: the fill must be a single character",
+        ),
+        ("format(r\"{x}\", [1])", "format: no value for {x}"),
+        ("format(r\"{}}\", [1])", "format: unmatched '}' in template"),
+        (
+            "let f = fn a -> a\nf(1, 2)",
+            "Function f requires 1 arguments, but got 2",
+        ),
+        (
+            "split(\",\", \"a,b\", 1)",
+            "Function split requires 2 arguments, but got 3",
+        ),
+        ("let x = 1\n2 |> x", "Pipe requires a function on the right"),
+        ("reduce(fn a, b -> a + b, [])", "reduce: list is empty"),
+        ("sort([1, \"a\"])", "sort: cannot compare string and int"),
+        ("sort([1, \"a\", 2])", "sort: cannot compare string and int"),
+        (
+            "sort([1.0, float(\"NaN\"), 0.5])",
+            "sort: cannot compare float and float",
+        ),
+        (
+            "sort_by(fn x -> [x], [1, \"a\"])",
+            "sort_by: cannot compare list and list",
+        ),
+        ("sort([1.5, 1, 1d])", "sort: cannot compare decimal and float"),
+        (
+            "sort([[1, \"a\"], [2], [1, 2]])",
+            "sort: cannot compare list and list",
+        ),
+        ("chunk(0, [1])", "chunk: size must be positive, got 0"),
+        (
+            "filter(fn x -> 1, [1, 2])",
+            "filter: the function must return a boolean, got int",
+        ),
+        (
+            "any(fn x -> \"yes\", [1])",
+            "any: the function must return a boolean, got string",
+        ),
+        (
+            "all(fn x -> (), [1])",
+            "all: the function must return a boolean, got unit",
+        ),
+        (
+            "count(fn x -> x, [1])",
+            "count: the function must return a boolean, got int",
+        ),
+        (
+            "partition(fn x -> none, [1])",
+            "partition: the function must return a boolean, got option",
+        ),
+        (
+            "find(fn x -> x, [1])",
+            "find: the function must return a boolean, got int",
+        ),
+        (
+            "sum([1, \"x\", 2])",
+            "sum: expected a list of numbers, but got string",
+        ),
+        (
+            "flat_map(fn x -> x, [1])",
+            "flat_map: the function must return a list, got int",
+        ),
+        ("fail(\"boom\")", "boom"),
+        ("assert(1 > 2, \"nope\")", "nope"),
+        (
+            "assert(1, \"nope\")",
+            "assert: expected a boolean and a message",
+        ),
+        (
+            "let user = {name: \"Ada\"}\n{ user with nmae: \"Bob\" }",
+            "Field not found: nmae",
+        ),
+        ("{ [1] with a: 1 }", "Update requires a record"),
+        (
+            "from_entries([[1, 2]])",
+            "from_entries: keys must be strings, but got int",
+        ),
+        ("1 is \"1\"", "Equality comparison requires same type"),
+        ("let f = fn x -> x\nf is f", "Functions cannot be compared"),
+        ("let f = fn x -> x\nlet g = [f]\ng is not [f]", "Functions cannot be compared"),
+        ("let f = fn x -> x\n1 in [f]", "Functions cannot be compared"),
+        ("unique([len, len])", "Functions cannot be compared"),
+        (
+            "{a: 1} < {a: 2}",
+            "Less than comparison requires two numbers, strings or lists, but got record and record",
+        ),
+        ("sort([len, len])", "sort: cannot compare function and function"),
+        // the fallback itself isn't protected
+        ("try 1 // 0 else 1 // 0", "Division by zero"),
+    ];
+
+    for (input, expected) in test_cases {
+        let (tree, vm) = run_both(input);
+        assert_eq!(
+            tree,
+            Err(expected.to_string()),
+            "Tree walker failed for: {}",
+            input
+        );
+        assert_eq!(vm, Err(expected.to_string()), "VM failed for: {}", input);
+    }
+}
+
+#[test]
+fn test_error_kinds_and_spans() {
+    let test_cases = vec![
+        ("fail(\"boom\")", ErrorKind::User, Some((1, 1))),
+        (
+            "1 + 1\n  assert(false, \"nope\")",
+            ErrorKind::Assertion,
+            Some((2, 3)),
+        ),
+        ("int(\"x\")", ErrorKind::Builtin, Some((1, 1))),
+        ("regex(\"(\")", ErrorKind::Builtin, Some((1, 1))),
+        // errors of builtins called back keep the span of their own call
+        (
+            "map(fn x -> fail(\"bad\"), [1])",
+            ErrorKind::User,
+            Some((1, 13)),
+        ),
+        ("1 // 0", ErrorKind::Runtime, None),
+    ];
+
+    for (input, kind, position) in test_cases {
+        let (tree, vm) = run_both_with_errors(input);
+        for (engine, result) in [("Tree walker", tree), ("VM", vm)] {
+            let error = result.expect_err(input);
+            assert_eq!(error.kind, kind, "{} failed for: {}", engine, input);
+            assert_eq!(
+                error.span.map(|span| (span.start.line, span.start.column)),
+                position,
+                "{} failed for: {}",
+                engine,
+                input
+            );
+        }
+    }
+}
+
+#[test]
+fn test_backtraces() {
+    let test_cases = vec![
+        (
+            "let check = fn x -> assert(x < 3, \"too big\")\nlet process = fn xs -> map(fn x -> check(x), xs)\n[1, 5] |> process",
+            vec![
+                ("assert", Some((1, 21))),
+                ("check", Some((2, 36))),
+                ("<lambda>", Some((2, 24))),
+                ("map", Some((2, 24))),
+                ("process", Some((3, 11))),
+            ],
+        ),
+        (
+            "let f = fn x -> 10 // x\nlet g = fn x -> f(x) + 1\ng(0)",
+            vec![("f", Some((2, 17))), ("g", Some((3, 1)))],
+        ),
+        // calls a `try` recovered from don't show up
+        (
+            "let f = fn x -> 10 // x\nlet g = fn x -> try f(x) else fail(\"no\")\ng(0)",
+            vec![("fail", Some((2, 31))), ("g", Some((3, 1)))],
+        ),
+    ];
+
+    for (input, expected) in test_cases {
+        let (tree, vm) = run_both_with_errors(input);
+        for (engine, result) in [("Tree walker", tree), ("VM", vm)] {
+            let trace: Vec<(String, Option<(usize, usize)>)> = result
+                .expect_err(input)
+                .trace
+                .into_iter()
+                .map(|frame| {
+                    let position = frame.span.map(|span| (span.start.line, span.start.column));
+                    (frame.function, position)
+                })
+                .collect();
+            let expected: Vec<(String, Option<(usize, usize)>)> = expected
+                .iter()
+                .map(|(function, position)| (function.to_string(), *position))
+                .collect();
+            assert_eq!(trace, expected, "{} failed for: {}", engine, input);
+        }
+    }
+}
+
+#[test]
+fn test_configured_io() {
+    let input = "print(\"a\")\nmap(fn x -> println(x), [1, 2])\nmatch read_line() | some(l) -> println(l) | none -> ()\nread_line()";
+    let mut ast = parse(input);
+    Resolver::new(Env::new(None).names())
+        .resolve(&mut ast)
+        .expect("failed to resolve");
+    let last = match ast.stmts.pop() {
+        Some(Stmt::Expr(expr)) => expr,
+        _ => panic!("program must end with an expression"),
+    };
+
+    for engine in ["Tree walker", "VM"] {
+        let stdout = Rc::new(RefCell::new(Vec::new()));
+        let io = Io {
+            stdout: stdout.clone() as Rc<RefCell<dyn Write>>,
+            stdin: Rc::new(RefCell::new(Cursor::new("hello\n"))),
+            sandbox: Rc::new(Sandbox::unrestricted()),
+            budget: Rc::new(Budget::default()),
+        };
+        let result = if engine == "VM" {
+            let mut vm = Vm::with_io(io);
+            vm.run(&ast).and_then(|_| vm.eval_expr(&last))
+        } else {
+            let mut env = Env::with_io(io);
+            eval(ast.clone(), &mut env).and_then(|_| eval_expr(&last, &mut env))
+        };
+        assert_eq!(result, Ok(Value::Option(None)), "{} failed", engine);
+        assert_eq!(
+            String::from_utf8(stdout.borrow().clone()).unwrap(),
+            "a1\n2\nhello\n",
+            "{} failed",
+            engine
+        );
+    }
+}